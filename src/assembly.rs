@@ -1,30 +1,816 @@
-use crate::{class::function::Function, constant::MAIN_FUNCTION_NAME};
+use crate::class::intrinsic::Intrinsic;
+use crate::class::location::Location;
+use crate::class::structure::{Layout, Struct};
+use crate::class::token::{BinaryOperator, UnaryOperator};
+use crate::constant::MAIN_FUNCTION_NAME;
+use crate::data_types::{DataType, IntegerType};
+use crate::ir::{IrFunction, Operation};
+use crate::vm::Value;
 use std::format as f;
 
-pub fn generate_assembly(functions: Vec<Function>) -> String {
+/// Size of the data stack in bytes. Register r15 points to its topmost value.
+const DATA_STACK_SIZE: usize = 1024 * 1024;
+/// Size of the heap in bytes where the structs are allocated
+const HEAP_SIZE: usize = 16 * 1024 * 1024;
+/// Registers of the syscall arguments in order. The syscall number is passed in rax.
+const SYSCALL_REGISTERS: [&str; 6] = ["rdi", "rsi", "rdx", "r10", "r8", "r9"];
+
+/// Generate x86-64 assembly (GNU assembler, Intel syntax) for Linux
+pub fn generate_assembly(functions: &[IrFunction]) -> String {
+    let mut strings: Vec<String> = Vec::new();
     let mut assembly: String = String::new();
+    assembly.push_str(".intel_syntax noprefix\n");
+    assembly.push_str(".globl _start\n\n");
+    assembly.push_str(".section .text\n");
+    let main: Option<&IrFunction> = functions
+        .iter()
+        .find(|function| function.name == MAIN_FUNCTION_NAME);
+    assembly.push_str(&program_start_assembly(main));
     for function in functions {
-        assembly.push_str(generate_function_assembly(&function).as_str());
+        assembly.push_str(&generate_function_assembly(function, &mut strings));
+    }
+    assembly.push_str(RUNTIME_ASSEMBLY);
+    assembly.push_str(&data_assembly(&strings, memory_size(functions)));
+    assembly
+}
+
+/// Memories are only allocated up to the end of the last one used by the functions
+fn memory_size(functions: &[IrFunction]) -> usize {
+    functions
+        .iter()
+        .flat_map(|function| &function.instructions)
+        .filter_map(|instruction| match &instruction.operation {
+            Operation::Push(Value::Array(typ, address)) => Some(address + typ.storage_size()),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Rot functions are prefixed so that they do not clash with the runtime routines
+fn function_label(function_name: &str) -> String {
+    f!("fun_{function_name}")
+}
+
+/// The entry point passes the command-line arguments from the initial process stack
+/// to `main` if it takes them and exits with the code returned by `main`
+fn program_start_assembly(main: Option<&IrFunction>) -> String {
+    let takes_arguments: bool = main.is_some_and(|main| !main.signature.parameters.is_empty());
+    let returns_code: bool = main.is_some_and(|main| !main.signature.return_type.is_empty());
+    let mut assembly = String::new();
+    assembly.push_str("_start:\n");
+    assembly.push_str("  lea r15, [rip + data_stack_end]\n");
+    assembly.push_str("  lea rax, [rip + heap]\n");
+    assembly.push_str("  mov [rip + heap_top], rax\n");
+    if takes_arguments {
+        assembly.push_str("  mov rax, [rsp]\n");
+        assembly.push_str(&push_register_assembly("rax"));
+        assembly.push_str("  lea rax, [rsp + 8]\n");
+        assembly.push_str(&push_register_assembly("rax"));
+    }
+    assembly.push_str(&f!("  call {}\n", function_label(MAIN_FUNCTION_NAME)));
+    assembly.push_str("  mov rax, 60\n");
+    match returns_code {
+        true => assembly.push_str(&pop_assembly("rdi")),
+        false => assembly.push_str("  xor rdi, rdi\n"),
     }
+    assembly.push_str("  syscall\n\n");
     assembly
 }
 
-fn generate_function_assembly(function: &Function) -> String {
-    let mut assembly: String = function_start_assembly(&function.name);
-    for token in &function.tokens {
+fn generate_function_assembly(function: &IrFunction, strings: &mut Vec<String>) -> String {
+    let mut assembly: String =
+        function_start_assembly(&function_label(&function.name), function.locals);
+    for instruction in &function.instructions {
+        assembly.push_str(&f!("  # {}\n", instruction.operation));
+        let operation_assembly: String = match &instruction.operation {
+            Operation::Push(value) => push_assembly(value, strings),
+            Operation::Binary(operator, typ) => {
+                let overflow: Option<usize> = overflow_string(typ, &instruction.location, strings);
+                let division: Option<usize> =
+                    matches!(operator, BinaryOperator::Division | BinaryOperator::Modulo)
+                        .then(|| division_string(&instruction.location, strings));
+                binary_assembly(operator, typ, overflow, division)
+            }
+            Operation::Unary(UnaryOperator::Not, _) => "  xor qword ptr [r15], 1\n".to_string(),
+            Operation::Unary(UnaryOperator::BitwiseNot, typ) => {
+                let mut assembly: String = "  not qword ptr [r15]\n".to_string();
+                // Inverted zero extended bits are not zero extended anymore
+                if let Some(typ) = typ.integer_type().filter(|typ| !typ.is_signed()) {
+                    assembly.push_str(&pop_assembly("rax"));
+                    assembly.push_str(&fit_integer_assembly("rax", typ, None));
+                    assembly.push_str(&push_register_assembly("rax"));
+                }
+                assembly
+            }
+            Operation::Intrinsic(Intrinsic::Assert, _) => {
+                // Failed assertion reports its location and exits the program
+                strings.push(f!("{}: Assertion failed\n", instruction.location));
+                let mut assembly: String = pop_assembly("rax");
+                assembly.push_str("  test rax, rax\n");
+                assembly.push_str(&f!("  lea rdi, [rip + string_{}]\n", strings.len() - 1));
+                assembly.push_str("  jz runtime_abort\n");
+                assembly
+            }
+            Operation::Intrinsic(Intrinsic::DivMod, typ) => {
+                let overflow: Option<usize> = overflow_string(typ, &instruction.location, strings);
+                let division: usize = division_string(&instruction.location, strings);
+                divmod_assembly(typ, overflow, division)
+            }
+            Operation::Intrinsic(intrinsic, typ) => intrinsic_assembly(intrinsic, typ),
+            Operation::Call(name) => f!("  call {}\n", function_label(name)),
+            Operation::Construct(structure) => construct_assembly(structure),
+            Operation::CheckIndex(length) => {
+                // Negative indices are out of bounds as well when compared as unsigned
+                strings.push(f!("{}: Index out of bounds\n", instruction.location));
+                let mut assembly: String = f!("  cmp qword ptr [r15], {length}\n");
+                assembly.push_str(&f!("  lea rdi, [rip + string_{}]\n", strings.len() - 1));
+                assembly.push_str("  jae runtime_abort\n");
+                assembly
+            }
+            Operation::LoadElement(typ) => {
+                let mut assembly: String = element_address_assembly(typ);
+                assembly.push_str(&load_assembly(
+                    "rax",
+                    "rax",
+                    0,
+                    typ.size(),
+                    is_sign_extended(typ, typ.size()),
+                ));
+                assembly.push_str(&push_register_assembly("rax"));
+                assembly
+            }
+            Operation::StoreElement(typ) => {
+                let mut assembly: String = element_address_assembly(typ);
+                assembly.push_str(&pop_assembly("rcx"));
+                assembly.push_str(&store_assembly("rax", 0, "rcx", typ.size()));
+                assembly
+            }
+            // Casts to the integers keep the lowest bits of the value. Other values
+            // are kept in their machine representation so only the type changes.
+            Operation::Cast(typ) => match typ.integer_type() {
+                Some(typ) => {
+                    let mut assembly: String = pop_assembly("rax");
+                    assembly.push_str(&fit_integer_assembly("rax", typ, None));
+                    assembly.push_str(&push_register_assembly("rax"));
+                    assembly
+                }
+                None => String::new(),
+            },
+            Operation::GetLocal(slot, _) => {
+                let mut assembly: String = f!("  mov rax, {}\n", local_address(*slot));
+                assembly.push_str(&push_register_assembly("rax"));
+                assembly
+            }
+            Operation::SetLocal(slot) => {
+                let mut assembly: String = pop_assembly("rax");
+                assembly.push_str(&f!("  mov {}, rax\n", local_address(*slot)));
+                assembly
+            }
+            Operation::Load(typ, offset) => {
+                let mut assembly: String = pop_assembly("rax");
+                assembly.push_str(&load_assembly(
+                    "rax",
+                    "rax",
+                    *offset,
+                    typ.size(),
+                    is_sign_extended(typ, typ.size()),
+                ));
+                assembly.push_str(&push_register_assembly("rax"));
+                assembly
+            }
+            Operation::Label(label) => f!("{}:\n", local_label(&function.name, *label)),
+            Operation::Jump(label) => f!("  jmp {}\n", local_label(&function.name, *label)),
+            Operation::JumpIfFalse(label) => {
+                let mut assembly: String = pop_assembly("rax");
+                assembly.push_str("  test rax, rax\n");
+                assembly.push_str(&f!("  jz {}\n", local_label(&function.name, *label)));
+                assembly
+            }
+            Operation::Return => function_end_assembly().to_string(),
+        };
+        assembly.push_str(&operation_assembly);
     }
-    assembly.push_str(function_end_assembly());
+    assembly.push('\n');
     assembly
 }
 
-fn function_start_assembly(function_name: &str) -> String {
+/// Local variables are stored in the slots below the frame pointer
+fn function_start_assembly(function_name: &str, locals: usize) -> String {
     let mut assembly = String::new();
     assembly.push_str(&f!("{function_name}:\n"));
     assembly.push_str("  push rbp\n");
     assembly.push_str("  mov rbp, rsp\n");
+    if locals > 0 {
+        assembly.push_str(&f!("  sub rsp, {}\n", locals * 8));
+    }
     assembly
 }
 
 fn function_end_assembly<'a>() -> &'a str {
-    "  pop rbp\n  ret\n\n"
+    "  mov rsp, rbp\n  pop rbp\n  ret\n"
+}
+
+fn local_address(slot: usize) -> String {
+    f!("qword ptr [rbp - {}]", (slot + 1) * 8)
+}
+
+fn local_label(function_name: &str, label: usize) -> String {
+    f!(".L{}_{label}", function_label(function_name))
+}
+
+fn push_assembly(value: &Value, strings: &mut Vec<String>) -> String {
+    let mut assembly: String = match value {
+        Value::Boolean(boolean) => f!("  mov rax, {}\n", *boolean as u8),
+        Value::Character(character) => f!("  mov rax, {}\n", *character as u32),
+        Value::Integer(integer) | Value::SizedInteger(_, integer) => f!("  mov rax, {integer}\n"),
+        Value::String(string) => {
+            strings.push(string.clone());
+            f!("  lea rax, [rip + string_{}]\n", strings.len() - 1)
+        }
+        Value::Array(_, address) => f!("  lea rax, [rip + memory + {address}]\n"),
+        Value::Struct(..) => unreachable!("Structs are constructed at runtime"),
+        Value::Pointer(..) => unreachable!("Pointers are created at runtime"),
+    };
+    assembly.push_str(&push_register_assembly("rax"));
+    assembly
+}
+
+fn push_register_assembly(register: &str) -> String {
+    f!("  sub r15, 8\n  mov [r15], {register}\n")
+}
+
+fn pop_assembly(register: &str) -> String {
+    f!("  mov {register}, [r15]\n  add r15, 8\n")
+}
+
+/// Allocate the struct from the heap and store the fields from the stack to their offsets
+fn construct_assembly(structure: &Struct) -> String {
+    let layout: Layout = structure.layout();
+    let mut assembly: String = f!("  mov rdi, {}\n", layout.size);
+    assembly.push_str("  call runtime_allocate\n");
+    for (field, offset) in structure.fields.iter().zip(&layout.offsets).rev() {
+        assembly.push_str(&pop_assembly("rbx"));
+        assembly.push_str(&store_assembly("rax", *offset, "rbx", field.typ.size()));
+    }
+    assembly.push_str(&push_register_assembly("rax"));
+    assembly
+}
+
+/// Pop the index and the array and compute the address of the element to rax
+fn element_address_assembly(typ: &DataType) -> String {
+    let mut assembly: String = pop_assembly("rbx");
+    assembly.push_str(&pop_assembly("rax"));
+    assembly.push_str(&f!("  lea rax, [rax + rbx * {}]\n", typ.size()));
+    assembly
+}
+
+/// Load `size` bytes from the address in `base` plus `offset` to `register`,
+/// sign extended if `signed` and zero extended otherwise
+fn load_assembly(register: &str, base: &str, offset: usize, size: usize, signed: bool) -> String {
+    match (size, signed) {
+        (1, true) => f!("  movsx {register}, byte ptr [{base} + {offset}]\n"),
+        (2, true) => f!("  movsx {register}, word ptr [{base} + {offset}]\n"),
+        (4, true) => f!("  movsxd {register}, dword ptr [{base} + {offset}]\n"),
+        (1, false) => f!("  movzx {register}, byte ptr [{base} + {offset}]\n"),
+        (2, false) => f!("  movzx {register}, word ptr [{base} + {offset}]\n"),
+        // Writing to the 32-bit register clears the upper half
+        (4, false) => f!(
+            "  mov {}, dword ptr [{base} + {offset}]\n",
+            register_part(register, size)
+        ),
+        _ => f!("  mov {register}, qword ptr [{base} + {offset}]\n"),
+    }
+}
+
+/// Signed integers narrower than `int` are sign extended when loaded with their size
+fn is_sign_extended(typ: &DataType, size: usize) -> bool {
+    size == typ.size() && typ.integer_type().is_some_and(IntegerType::is_signed)
+}
+
+/// Error message reported when an operation on the checked integer type overflows at the location
+fn overflow_string(
+    typ: &DataType,
+    location: &Location,
+    strings: &mut Vec<String>,
+) -> Option<usize> {
+    typ.integer_type().filter(|typ| typ.is_checked())?;
+    strings.push(f!("{location}: Integer overflow\n"));
+    Some(strings.len() - 1)
+}
+
+/// Error message reported when the divisor of the division at the location is zero
+fn division_string(location: &Location, strings: &mut Vec<String>) -> usize {
+    strings.push(f!("{location}: Division by zero\n"));
+    strings.len() - 1
+}
+
+/// Make the value in `register` a value of the integer type. Checked types abort with
+/// the `overflow` string if the value does not fit and others keep its lowest bits.
+fn fit_integer_assembly(register: &str, typ: IntegerType, overflow: Option<usize>) -> String {
+    let part: String = register_part(register, typ.size());
+    let extend: &str = match (typ.size(), typ.is_signed()) {
+        (8, _) => return String::new(),
+        (4, true) => "movsxd",
+        // Writing to the 32-bit register clears the upper half
+        (4, false) => return f!("  mov {part}, {part}\n"),
+        (_, true) => "movsx",
+        (_, false) => "movzx",
+    };
+    match overflow.filter(|_| typ.is_checked()) {
+        Some(string) => {
+            let mut assembly: String = f!("  {extend} rcx, {part}\n");
+            assembly.push_str(&f!("  cmp rcx, {register}\n"));
+            assembly.push_str(&f!("  lea rdi, [rip + string_{string}]\n"));
+            assembly.push_str("  jne runtime_abort\n");
+            assembly
+        }
+        None => f!("  {extend} {register}, {part}\n"),
+    }
 }
+
+/// Store the lowest `size` bytes of `register` to the address in `base` plus `offset`
+fn store_assembly(base: &str, offset: usize, register: &str, size: usize) -> String {
+    let (width, register): (&str, String) = match size {
+        1 => ("byte", register_part(register, size)),
+        2 => ("word", register_part(register, size)),
+        4 => ("dword", register_part(register, size)),
+        _ => ("qword", register.to_string()),
+    };
+    f!("  mov {width} ptr [{base} + {offset}], {register}\n")
+}
+
+/// Name of the lowest `size` bytes of a general purpose register, e.g. `ebx` of `rbx`
+fn register_part(register: &str, size: usize) -> String {
+    let name: &str = register.trim_start_matches('r');
+    match (size, name) {
+        (1, "ax" | "bx" | "cx" | "dx") => f!("{}l", &name[..1]),
+        (2, "ax" | "bx" | "cx" | "dx" | "si" | "di") => name.to_string(),
+        (4, "ax" | "bx" | "cx" | "dx" | "si" | "di") => f!("e{name}"),
+        (1, _) => f!("{register}b"),
+        (2, _) => f!("{register}w"),
+        (4, _) => f!("{register}d"),
+        _ => register.to_string(),
+    }
+}
+
+/// Operation on the two topmost values. Division and modulo abort with the `division`
+/// string if the divisor is zero.
+fn binary_assembly(
+    operator: &BinaryOperator,
+    typ: &DataType,
+    overflow: Option<usize>,
+    division: Option<usize>,
+) -> String {
+    let mut assembly: String = pop_assembly("rbx");
+    assembly.push_str(&pop_assembly("rax"));
+    match typ.integer_type() {
+        Some(IntegerType::I64) | None => {}
+        Some(typ) => return sized_binary_assembly(assembly, operator, typ, overflow, division),
+    }
+    let operation: String = match (operator, typ) {
+        (BinaryOperator::Addition, _) => "  add rax, rbx\n".to_string(),
+        (BinaryOperator::Subtraction, _) => "  sub rax, rbx\n".to_string(),
+        (BinaryOperator::Multiplication, _) => "  imul rax, rbx\n".to_string(),
+        (BinaryOperator::Division, _) => division_assembly(IntegerType::I64, division),
+        (BinaryOperator::Modulo, _) => {
+            f!(
+                "{}  mov rax, rdx\n",
+                division_assembly(IntegerType::I64, division)
+            )
+        }
+        // Booleans are 0 or 1 so the logical operators are bitwise on them
+        (BinaryOperator::BitwiseAnd | BinaryOperator::And, _) => "  and rax, rbx\n".to_string(),
+        (BinaryOperator::BitwiseOr | BinaryOperator::Or, _) => "  or rax, rbx\n".to_string(),
+        (BinaryOperator::BitwiseXor, _) => "  xor rax, rbx\n".to_string(),
+        (BinaryOperator::ShiftLeft, _) => "  mov rcx, rbx\n  shl rax, cl\n".to_string(),
+        (BinaryOperator::ShiftRight, _) => "  mov rcx, rbx\n  sar rax, cl\n".to_string(),
+        (BinaryOperator::Equals, DataType::String) => {
+            "  mov rdi, rax\n  mov rsi, rbx\n  call runtime_string_equals\n".to_string()
+        }
+        (BinaryOperator::NotEquals, DataType::String) => {
+            "  mov rdi, rax\n  mov rsi, rbx\n  call runtime_string_equals\n  xor rax, 1\n"
+                .to_string()
+        }
+        // Addresses are compared as unsigned numbers
+        (BinaryOperator::GreaterOrEqual, DataType::Pointer(_)) => comparison_assembly("setae"),
+        (BinaryOperator::GreaterThan, DataType::Pointer(_)) => comparison_assembly("seta"),
+        (BinaryOperator::LessOrEqual, DataType::Pointer(_)) => comparison_assembly("setbe"),
+        (BinaryOperator::LessThan, DataType::Pointer(_)) => comparison_assembly("setb"),
+        (BinaryOperator::Equals, _) => comparison_assembly("sete"),
+        (BinaryOperator::NotEquals, _) => comparison_assembly("setne"),
+        (BinaryOperator::GreaterOrEqual, _) => comparison_assembly("setge"),
+        (BinaryOperator::GreaterThan, _) => comparison_assembly("setg"),
+        (BinaryOperator::LessOrEqual, _) => comparison_assembly("setle"),
+        (BinaryOperator::LessThan, _) => comparison_assembly("setl"),
+        (BinaryOperator::Assignment, _) => {
+            unreachable!("Assignment is rejected by the type checker")
+        }
+    };
+    assembly.push_str(&operation);
+    assembly.push_str(&push_register_assembly("rax"));
+    assembly
+}
+
+/// Operation on the integers other than `int` where an `int` operand of the arithmetic is
+/// converted to the type of the other one. The result has to fit in the type as well.
+/// Comparisons with an `int` are done as `int` and do not get here.
+fn sized_binary_assembly(
+    mut assembly: String,
+    operator: &BinaryOperator,
+    typ: IntegerType,
+    overflow: Option<usize>,
+    division: Option<usize>,
+) -> String {
+    // Amount of the shift can be of any integer type
+    if !matches!(
+        operator,
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight
+    ) {
+        assembly.push_str(&fit_integer_assembly("rax", typ, overflow));
+        assembly.push_str(&fit_integer_assembly("rbx", typ, overflow));
+    }
+    let (operation, fit_result): (String, bool) = match operator {
+        BinaryOperator::Addition => ("  add rax, rbx\n".to_string(), true),
+        BinaryOperator::Subtraction => ("  sub rax, rbx\n".to_string(), true),
+        BinaryOperator::Multiplication => ("  imul rax, rbx\n".to_string(), true),
+        BinaryOperator::Division => (division_assembly(typ, division), true),
+        BinaryOperator::Modulo => (
+            f!("{}  mov rax, rdx\n", division_assembly(typ, division)),
+            false,
+        ),
+        BinaryOperator::BitwiseAnd => ("  and rax, rbx\n".to_string(), false),
+        BinaryOperator::BitwiseOr => ("  or rax, rbx\n".to_string(), false),
+        BinaryOperator::BitwiseXor => ("  xor rax, rbx\n".to_string(), false),
+        // Shifted out bits are dropped even from the checked types
+        BinaryOperator::ShiftLeft => {
+            let mut operation: String = "  mov rcx, rbx\n  shl rax, cl\n".to_string();
+            operation.push_str(&fit_integer_assembly("rax", typ, None));
+            (operation, false)
+        }
+        BinaryOperator::ShiftRight if typ.is_signed() => {
+            ("  mov rcx, rbx\n  sar rax, cl\n".to_string(), false)
+        }
+        BinaryOperator::ShiftRight => ("  mov rcx, rbx\n  shr rax, cl\n".to_string(), false),
+        BinaryOperator::Equals => (comparison_assembly("sete"), false),
+        BinaryOperator::NotEquals => (comparison_assembly("setne"), false),
+        BinaryOperator::GreaterOrEqual if typ.is_signed() => (comparison_assembly("setge"), false),
+        BinaryOperator::GreaterThan if typ.is_signed() => (comparison_assembly("setg"), false),
+        BinaryOperator::LessOrEqual if typ.is_signed() => (comparison_assembly("setle"), false),
+        BinaryOperator::LessThan if typ.is_signed() => (comparison_assembly("setl"), false),
+        BinaryOperator::GreaterOrEqual => (comparison_assembly("setae"), false),
+        BinaryOperator::GreaterThan => (comparison_assembly("seta"), false),
+        BinaryOperator::LessOrEqual => (comparison_assembly("setbe"), false),
+        BinaryOperator::LessThan => (comparison_assembly("setb"), false),
+        BinaryOperator::Assignment | BinaryOperator::And | BinaryOperator::Or => {
+            unreachable!("{operator:?} on {typ} is rejected by the type checker")
+        }
+    };
+    assembly.push_str(&operation);
+    if fit_result {
+        assembly.push_str(&fit_integer_assembly("rax", typ, overflow));
+    }
+    assembly.push_str(&push_register_assembly("rax"));
+    assembly
+}
+
+/// Divide rax by rbx to the quotient in rax and the remainder in rdx. A zero divisor
+/// aborts with the `division` string and the minimum divided by -1 wraps around.
+fn division_assembly(typ: IntegerType, division: Option<usize>) -> String {
+    let mut assembly = String::new();
+    if let Some(division) = division {
+        assembly.push_str("  test rbx, rbx\n");
+        assembly.push_str(&f!("  lea rdi, [rip + string_{division}]\n"));
+        assembly.push_str("  jz runtime_abort\n");
+    }
+    match typ.is_signed() {
+        true => {
+            // Dividing by -1 is negating which `idiv` faults on for the minimum
+            assembly.push_str("  cmp rbx, -1\n  jne 1f\n  neg rax\n  mov rbx, 1\n1:\n");
+            assembly.push_str("  cqo\n  idiv rbx\n");
+        }
+        false => assembly.push_str("  xor edx, edx\n  div rbx\n"),
+    }
+    assembly
+}
+
+fn divmod_assembly(typ: &DataType, overflow: Option<usize>, division: usize) -> String {
+    let typ: IntegerType = typ.integer_type().unwrap_or(IntegerType::I64);
+    let mut assembly: String = pop_assembly("rbx");
+    assembly.push_str(&pop_assembly("rax"));
+    assembly.push_str(&fit_integer_assembly("rax", typ, overflow));
+    assembly.push_str(&fit_integer_assembly("rbx", typ, overflow));
+    assembly.push_str(&division_assembly(typ, Some(division)));
+    assembly.push_str(&fit_integer_assembly("rax", typ, overflow));
+    assembly.push_str(&push_register_assembly("rax"));
+    assembly.push_str(&push_register_assembly("rdx"));
+    assembly
+}
+
+fn comparison_assembly(set_instruction: &str) -> String {
+    f!("  cmp rax, rbx\n  {set_instruction} al\n  movzx rax, al\n")
+}
+
+fn intrinsic_assembly(intrinsic: &Intrinsic, typ: &DataType) -> String {
+    match intrinsic {
+        Intrinsic::Drop => "  add r15, 8\n".to_string(),
+        Intrinsic::Dup => {
+            let mut assembly: String = "  mov rax, [r15]\n".to_string();
+            assembly.push_str(&push_register_assembly("rax"));
+            assembly
+        }
+        Intrinsic::Over => {
+            let mut assembly: String = "  mov rax, [r15 + 8]\n".to_string();
+            assembly.push_str(&push_register_assembly("rax"));
+            assembly
+        }
+        Intrinsic::Print => {
+            let routine: &str = match typ {
+                DataType::Boolean => "runtime_print_bool",
+                DataType::Character => "runtime_print_char",
+                DataType::String => "runtime_print_string",
+                DataType::SizedInteger(IntegerType::U64) => "runtime_print_uint",
+                _ => "runtime_print_int",
+            };
+            let mut assembly: String = pop_assembly("rdi");
+            assembly.push_str(&f!("  call {routine}\n"));
+            assembly
+        }
+        Intrinsic::Swap => {
+            let mut assembly: String = "  mov rax, [r15]\n".to_string();
+            assembly.push_str("  mov rbx, [r15 + 8]\n");
+            assembly.push_str("  mov [r15], rbx\n");
+            assembly.push_str("  mov [r15 + 8], rax\n");
+            assembly
+        }
+        Intrinsic::Load8 | Intrinsic::Load16 | Intrinsic::Load32 | Intrinsic::Load64 => {
+            let mut assembly: String = pop_assembly("rax");
+            let size: usize = intrinsic.access_size().unwrap();
+            assembly.push_str(&load_assembly(
+                "rax",
+                "rax",
+                0,
+                size,
+                is_sign_extended(&typ.loaded_type(size), size),
+            ));
+            assembly.push_str(&push_register_assembly("rax"));
+            assembly
+        }
+        Intrinsic::Store8 | Intrinsic::Store16 | Intrinsic::Store32 | Intrinsic::Store64 => {
+            let mut assembly: String = pop_assembly("rax");
+            assembly.push_str(&pop_assembly("rbx"));
+            assembly.push_str(&store_assembly(
+                "rax",
+                0,
+                "rbx",
+                intrinsic.access_size().unwrap(),
+            ));
+            assembly
+        }
+        Intrinsic::Syscall0
+        | Intrinsic::Syscall1
+        | Intrinsic::Syscall2
+        | Intrinsic::Syscall3
+        | Intrinsic::Syscall4
+        | Intrinsic::Syscall5
+        | Intrinsic::Syscall6 => {
+            let count: usize = intrinsic.syscall_arguments().unwrap();
+            let mut assembly: String = pop_assembly("rax");
+            for register in SYSCALL_REGISTERS[..count].iter().rev() {
+                assembly.push_str(&pop_assembly(register));
+            }
+            assembly.push_str("  syscall\n");
+            assembly.push_str(&push_register_assembly("rax"));
+            assembly
+        }
+        Intrinsic::Assert => unreachable!("Assertions are generated with their location"),
+        Intrinsic::DivMod => unreachable!("Division is generated with its location"),
+    }
+}
+
+/// String literals are stored as null-terminated byte arrays
+fn data_assembly(strings: &[String], memory_size: usize) -> String {
+    let mut assembly: String = String::new();
+    assembly.push_str(".section .rodata\n");
+    assembly.push_str("true_string: .ascii \"true\\n\"\n");
+    assembly.push_str("false_string: .ascii \"false\\n\"\n");
+    assembly.push_str("newline_string: .ascii \"\\n\"\n");
+    assembly.push_str("out_of_memory_string: .ascii \"Out of memory\\n\"\n");
+    for (i, string) in strings.iter().enumerate() {
+        let bytes: Vec<String> = string
+            .bytes()
+            .chain(std::iter::once(0))
+            .map(|byte| byte.to_string())
+            .collect();
+        assembly.push_str(&f!("string_{i}: .byte {}\n", bytes.join(", ")));
+    }
+    assembly.push_str("\n.section .bss\n");
+    assembly.push_str(&f!("data_stack: .skip {DATA_STACK_SIZE}\n"));
+    assembly.push_str("data_stack_end:\n");
+    assembly.push_str("heap_top: .skip 8\n");
+    assembly.push_str(&f!("heap: .skip {HEAP_SIZE}\n"));
+    assembly.push_str("heap_end:\n");
+    assembly.push_str(".balign 8\n");
+    assembly.push_str(&f!("memory: .skip {memory_size}\n"));
+    assembly
+}
+
+/// Routines called from the generated code. Arguments are passed in rdi and rsi
+/// and `runtime_allocate` returns the address of the allocated memory in rax.
+const RUNTIME_ASSEMBLY: &str = "\
+runtime_print_int:
+  mov r8, rdi
+  test rdi, rdi
+  jns 1f
+  neg rdi
+  jmp 1f
+runtime_print_uint:
+  xor r8, r8
+1:
+  push rbp
+  mov rbp, rsp
+  sub rsp, 32
+  mov rax, rdi
+  lea rsi, [rbp - 1]
+  mov byte ptr [rsi], 10
+  mov rcx, 10
+2:
+  xor rdx, rdx
+  div rcx
+  add dl, 48
+  dec rsi
+  mov [rsi], dl
+  test rax, rax
+  jnz 2b
+  test r8, r8
+  jns 3f
+  dec rsi
+  mov byte ptr [rsi], 45
+3:
+  mov rdx, rbp
+  sub rdx, rsi
+  mov rax, 1
+  mov rdi, 1
+  syscall
+  mov rsp, rbp
+  pop rbp
+  ret
+
+runtime_print_bool:
+  lea rsi, [rip + true_string]
+  mov rdx, 5
+  test rdi, rdi
+  jnz 1f
+  lea rsi, [rip + false_string]
+  mov rdx, 6
+1:
+  mov rax, 1
+  mov rdi, 1
+  syscall
+  ret
+
+runtime_print_char:
+  push rbp
+  mov rbp, rsp
+  sub rsp, 16
+  mov rsi, rsp
+  mov eax, edi
+  cmp eax, 0x80
+  jb 1f
+  cmp eax, 0x800
+  jb 2f
+  cmp eax, 0x10000
+  jb 3f
+  mov ecx, eax
+  shr ecx, 18
+  or cl, 0xF0
+  mov [rsi], cl
+  mov ecx, eax
+  shr ecx, 12
+  and cl, 0x3F
+  or cl, 0x80
+  mov [rsi + 1], cl
+  mov ecx, eax
+  shr ecx, 6
+  and cl, 0x3F
+  or cl, 0x80
+  mov [rsi + 2], cl
+  mov ecx, eax
+  and cl, 0x3F
+  or cl, 0x80
+  mov [rsi + 3], cl
+  mov rdx, 4
+  jmp 4f
+3:
+  mov ecx, eax
+  shr ecx, 12
+  or cl, 0xE0
+  mov [rsi], cl
+  mov ecx, eax
+  shr ecx, 6
+  and cl, 0x3F
+  or cl, 0x80
+  mov [rsi + 1], cl
+  mov ecx, eax
+  and cl, 0x3F
+  or cl, 0x80
+  mov [rsi + 2], cl
+  mov rdx, 3
+  jmp 4f
+2:
+  mov ecx, eax
+  shr ecx, 6
+  or cl, 0xC0
+  mov [rsi], cl
+  mov ecx, eax
+  and cl, 0x3F
+  or cl, 0x80
+  mov [rsi + 1], cl
+  mov rdx, 2
+  jmp 4f
+1:
+  mov [rsi], al
+  mov rdx, 1
+4:
+  mov byte ptr [rsi + rdx], 10
+  inc rdx
+  mov rax, 1
+  mov rdi, 1
+  syscall
+  mov rsp, rbp
+  pop rbp
+  ret
+
+runtime_print_string:
+  mov rsi, rdi
+  xor rdx, rdx
+1:
+  cmp byte ptr [rsi + rdx], 0
+  je 2f
+  inc rdx
+  jmp 1b
+2:
+  mov rax, 1
+  mov rdi, 1
+  syscall
+  mov rax, 1
+  mov rdi, 1
+  lea rsi, [rip + newline_string]
+  mov rdx, 1
+  syscall
+  ret
+
+runtime_abort:
+  mov rsi, rdi
+  xor rdx, rdx
+1:
+  cmp byte ptr [rsi + rdx], 0
+  je 2f
+  inc rdx
+  jmp 1b
+2:
+  mov rax, 1
+  mov rdi, 2
+  syscall
+  mov rax, 60
+  mov rdi, 1
+  syscall
+
+runtime_allocate:
+  mov rax, [rip + heap_top]
+  add rdi, 7
+  and rdi, -8
+  lea rsi, [rax + rdi]
+  lea rcx, [rip + heap_end]
+  cmp rsi, rcx
+  ja 1f
+  mov [rip + heap_top], rsi
+  ret
+1:
+  mov rax, 1
+  mov rdi, 2
+  lea rsi, [rip + out_of_memory_string]
+  mov rdx, 14
+  syscall
+  mov rax, 60
+  mov rdi, 1
+  syscall
+
+runtime_string_equals:
+  xor rcx, rcx
+1:
+  mov al, [rdi + rcx]
+  cmp al, [rsi + rcx]
+  jne 2f
+  test al, al
+  jz 3f
+  inc rcx
+  jmp 1b
+2:
+  xor rax, rax
+  ret
+3:
+  mov rax, 1
+  ret
+
+";
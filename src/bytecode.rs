@@ -0,0 +1,943 @@
+use std::collections::HashMap;
+
+use strum::IntoEnumIterator;
+
+use crate::class::expression::{Expression, ExpressionType};
+use crate::class::intrinsic::{intrinsic_from_string, Intrinsic};
+use crate::class::location::Location;
+use crate::class::program::Program;
+use crate::class::statement::{Statement, StatementType};
+use crate::class::token::{BinaryOperator, UnaryOperator};
+use crate::compiler::CompilerError;
+use crate::data_types::{DataType, IntegerType};
+use crate::vm::Value;
+
+const BYTECODE_MAGIC: &[u8; 4] = b"ROTC";
+const BYTECODE_VERSION: u8 = 6;
+const NO_FILE: u32 = u32::MAX;
+/// Sizes read from a file are allocated by the virtual machine so they are limited
+/// to keep a malformed file from exhausting the memory
+const MAX_MEMORY_SIZE: usize = 256 * 1024 * 1024;
+const MAX_LOCALS: usize = 65_536;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// Push a value from the constant pool
+    Push(usize),
+    Call(usize),
+    Intrinsic(Intrinsic),
+    Binary(BinaryOperator),
+    Unary(UnaryOperator),
+    /// Construct the struct at the index from its field values on the stack
+    Construct(usize),
+    /// Replace the struct on the top of the stack with the value of its field
+    Field(String),
+    /// Replace the array and the index on the top of the stack with the element
+    Load,
+    /// Store the value below the array and the index to the element
+    Store,
+    /// Convert the value on the top of the stack to the type
+    Cast(DataType),
+    /// Push the value of the local variable in the slot of the frame
+    GetLocal(usize),
+    /// Pop the value on the top of the stack to the local variable in the slot
+    SetLocal(usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BytecodeFunction {
+    pub name: String,
+    /// Number of values the function takes from the stack
+    pub parameters: usize,
+    /// Number of values the function leaves on the stack
+    pub returns: usize,
+    /// Number of local variable slots in the frame of the function
+    pub locals: usize,
+    pub instructions: Vec<Instruction>,
+    /// Source location of each instruction, used for stack traces
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BytecodeStruct {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bytecode {
+    pub constants: Vec<Value>,
+    pub structs: Vec<BytecodeStruct>,
+    /// Number of bytes needed for the memories of the program
+    pub memory_size: usize,
+    pub functions: Vec<BytecodeFunction>,
+    /// Index of the function where the execution starts
+    pub entry: usize,
+}
+
+/// Compile functions to bytecode where the execution starts from the `entry` function
+pub fn compile_bytecode(program: &Program, entry: &str) -> Result<Bytecode, CompilerError> {
+    let function_indices: HashMap<&str, usize> = program
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(i, function)| (function.name.as_str(), i))
+        .collect();
+    let entry: usize = match function_indices.get(entry) {
+        Some(index) => *index,
+        None => {
+            return Err(CompilerError::BytecodeError(
+                None,
+                format!("The '{entry}' function is not defined"),
+            ))
+        }
+    };
+
+    let mut constants: Vec<Value> = Vec::new();
+    let mut bytecode_functions: Vec<BytecodeFunction> = Vec::new();
+    for function in &program.functions {
+        let mut compiler = FunctionCompiler {
+            program,
+            function_indices: &function_indices,
+            constants: &mut constants,
+            instructions: Vec::new(),
+            locations: Vec::new(),
+            loops: Vec::new(),
+            locals: Vec::new(),
+            local_count: 0,
+        };
+        let variables = function
+            .signature
+            .parameters
+            .iter()
+            .map(|parameter| parameter.name.clone());
+        compiler.bind_locals(variables.collect(), &function.location);
+        for statement in &function.statements {
+            compiler.compile_statement(statement)?;
+        }
+        let end_location: Location = match function.tokens.last() {
+            Some(token) => token.location.clone(),
+            None => function.location.clone(),
+        };
+        compiler.emit(Instruction::Return, &end_location);
+        bytecode_functions.push(BytecodeFunction {
+            name: function.name.clone(),
+            parameters: function.signature.parameters.len(),
+            returns: function.signature.return_type.len(),
+            locals: compiler.local_count,
+            instructions: compiler.instructions,
+            locations: compiler.locations,
+        });
+    }
+    let structs: Vec<BytecodeStruct> = program
+        .structs
+        .iter()
+        .map(|structure| BytecodeStruct {
+            name: structure.name.clone(),
+            fields: structure
+                .fields
+                .iter()
+                .map(|field| field.name.clone())
+                .collect(),
+        })
+        .collect();
+    Ok(Bytecode {
+        constants,
+        structs,
+        memory_size: program.memory_size(),
+        functions: bytecode_functions,
+        entry,
+    })
+}
+
+struct LoopLabels {
+    start: usize,
+    breaks: Vec<usize>,
+}
+
+struct FunctionCompiler<'a> {
+    program: &'a Program,
+    function_indices: &'a HashMap<&'a str, usize>,
+    constants: &'a mut Vec<Value>,
+    instructions: Vec<Instruction>,
+    locations: Vec<Location>,
+    loops: Vec<LoopLabels>,
+    /// Names of the local variables in scope where the index is the slot of the variable
+    locals: Vec<String>,
+    /// Number of slots needed by the local variables of the function
+    local_count: usize,
+}
+
+impl FunctionCompiler<'_> {
+    fn emit(&mut self, instruction: Instruction, location: &Location) -> usize {
+        self.instructions.push(instruction);
+        self.locations.push(location.clone());
+        self.instructions.len() - 1
+    }
+
+    /// Push the value adding it to the constant pool if it is not there yet
+    fn emit_push(&mut self, constant: Value, location: &Location) {
+        let index: usize = match self.constants.iter().position(|c| *c == constant) {
+            Some(index) => index,
+            None => {
+                self.constants.push(constant);
+                self.constants.len() - 1
+            }
+        };
+        self.emit(Instruction::Push(index), location);
+    }
+
+    /// Point the jump at `index` to the next emitted instruction
+    fn patch_jump(&mut self, index: usize) {
+        let target: usize = self.instructions.len();
+        match &mut self.instructions[index] {
+            Instruction::Jump(address) | Instruction::JumpIfFalse(address) => *address = target,
+            instruction => unreachable!("Cannot patch non-jump instruction {instruction:?}"),
+        }
+    }
+
+    /// Slot of the innermost local variable called `name`
+    fn local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local == name)
+    }
+
+    /// Pop the values on the top of the stack to new local variables
+    fn bind_locals(&mut self, names: Vec<String>, location: &Location) {
+        let scope: usize = self.locals.len();
+        self.locals.extend(names);
+        self.local_count = self.local_count.max(self.locals.len());
+        // The last variable is on the top of the stack
+        for slot in (scope..self.locals.len()).rev() {
+            self.emit(Instruction::SetLocal(slot), location);
+        }
+    }
+
+    fn compile_statements(&mut self, statement: &Statement) -> Result<(), CompilerError> {
+        for statement in statement.statements.iter().flatten() {
+            self.compile_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<(), CompilerError> {
+        let location: &Location = &statement.location;
+        match &statement.typ {
+            StatementType::Expression => {
+                if let Some(expression) = &statement.expression {
+                    self.compile_expression(expression)?
+                }
+            }
+            StatementType::Compound => self.compile_statements(statement)?,
+            StatementType::Binding => {
+                let (body, variables) =
+                    statement.statements.as_ref().unwrap().split_last().unwrap();
+                let scope: usize = self.locals.len();
+                let names = variables
+                    .iter()
+                    .map(|variable| variable.value.clone().unwrap_or_default());
+                self.bind_locals(names.collect(), location);
+                self.compile_statements(body)?;
+                self.locals.truncate(scope);
+            }
+            StatementType::Conditional => {
+                let branches: &Vec<Statement> = statement.statements.as_ref().unwrap();
+                self.compile_statements(&branches[0])?;
+                let jump_to_else: usize = self.emit(Instruction::JumpIfFalse(0), location);
+                self.compile_statements(&branches[1])?;
+                match branches.get(2) {
+                    Some(else_branch) => {
+                        let jump_to_end: usize = self.emit(Instruction::Jump(0), location);
+                        self.patch_jump(jump_to_else);
+                        self.compile_statement(else_branch)?;
+                        self.patch_jump(jump_to_end);
+                    }
+                    None => self.patch_jump(jump_to_else),
+                }
+            }
+            StatementType::Loop => {
+                let branches: &Vec<Statement> = statement.statements.as_ref().unwrap();
+                let start: usize = self.instructions.len();
+                self.compile_statements(&branches[0])?;
+                let jump_to_end: usize = self.emit(Instruction::JumpIfFalse(0), location);
+                self.loops.push(LoopLabels {
+                    start,
+                    breaks: Vec::new(),
+                });
+                self.compile_statements(&branches[1])?;
+                self.emit(Instruction::Jump(start), location);
+                let labels: LoopLabels = self.loops.pop().unwrap();
+                self.patch_jump(jump_to_end);
+                for jump in labels.breaks {
+                    self.patch_jump(jump);
+                }
+            }
+            StatementType::Break => {
+                let jump: usize = self.emit(Instruction::Jump(0), location);
+                match self.loops.last_mut() {
+                    Some(labels) => labels.breaks.push(jump),
+                    None => {
+                        return Err(CompilerError::BytecodeError(
+                            Some(location.clone()),
+                            "'break' outside of a loop".to_string(),
+                        ))
+                    }
+                }
+            }
+            StatementType::Continue => match self.loops.last() {
+                Some(labels) => {
+                    let start: usize = labels.start;
+                    self.emit(Instruction::Jump(start), location);
+                }
+                None => {
+                    return Err(CompilerError::BytecodeError(
+                        Some(location.clone()),
+                        "'continue' outside of a loop".to_string(),
+                    ))
+                }
+            },
+            StatementType::Return => {
+                self.emit(Instruction::Return, location);
+            }
+            StatementType::NoOperation => {}
+            typ => {
+                return Err(CompilerError::BytecodeError(
+                    Some(location.clone()),
+                    format!("Statement {typ:?} is not supported"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_children(&mut self, expression: &Expression) -> Result<(), CompilerError> {
+        for expression in expression.expressions.iter().flatten() {
+            self.compile_expression(expression)?;
+        }
+        Ok(())
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<(), CompilerError> {
+        let location: &Location = &expression.location;
+        let value: &str = expression.value.as_deref().unwrap_or_default();
+        match &expression.typ {
+            ExpressionType::Literal(data_type) => {
+                let constant: Value = literal_value(data_type, value, location)?;
+                self.emit_push(constant, location);
+            }
+            ExpressionType::Binary(BinaryOperator::Assignment) => {
+                match expression.expressions.as_deref() {
+                    Some([variable]) if variable.typ == ExpressionType::Identifier => {
+                        let name: &str = variable.value.as_deref().unwrap_or_default();
+                        match self.local(name) {
+                            Some(slot) => self.emit(Instruction::SetLocal(slot), location),
+                            None => {
+                                return Err(CompilerError::BytecodeError(
+                                    Some(location.clone()),
+                                    format!("Unknown local variable '{name}'"),
+                                ))
+                            }
+                        };
+                    }
+                    Some([index]) => {
+                        self.compile_children(index)?;
+                        self.emit(Instruction::Store, location);
+                    }
+                    _ => {
+                        return Err(CompilerError::BytecodeError(
+                            Some(location.clone()),
+                            "Assignment is not supported".to_string(),
+                        ))
+                    }
+                }
+            }
+            ExpressionType::Binary(operator) => {
+                self.emit(Instruction::Binary(operator.clone()), location);
+            }
+            ExpressionType::Unary(operator) => {
+                self.emit(Instruction::Unary(operator.clone()), location);
+            }
+            ExpressionType::Identifier | ExpressionType::FunctionCall => {
+                // Arguments of calls are pushed in order before calling
+                self.compile_children(expression)?;
+                if let Some(slot) = self.local(value) {
+                    self.emit(Instruction::GetLocal(slot), location);
+                } else if let Some(intrinsic) = intrinsic_from_string(value) {
+                    self.emit(Instruction::Intrinsic(intrinsic), location);
+                } else if let Some(index) = self.function_indices.get(value) {
+                    self.emit(Instruction::Call(*index), location);
+                } else if let Some(index) =
+                    self.program.structs.iter().position(|s| s.name == value)
+                {
+                    self.emit(Instruction::Construct(index), location);
+                } else if let Some((address, memory)) = self.program.memory(value) {
+                    self.emit_push(Value::Array(memory.typ.clone(), address), location);
+                } else {
+                    return Err(CompilerError::BytecodeError(
+                        Some(location.clone()),
+                        format!("Unknown word '{value}'"),
+                    ));
+                }
+            }
+            ExpressionType::FieldAccess => {
+                self.compile_children(expression)?;
+                self.emit(Instruction::Field(value.to_string()), location);
+            }
+            ExpressionType::Index => {
+                self.compile_children(expression)?;
+                self.emit(Instruction::Load, location);
+            }
+            ExpressionType::Cast(typ) => {
+                self.emit(Instruction::Cast(typ.clone()), location);
+            }
+            ExpressionType::Enclosure => self.compile_children(expression)?,
+        }
+        Ok(())
+    }
+}
+
+pub fn literal_value(
+    data_type: &DataType,
+    literal: &str,
+    location: &Location,
+) -> Result<Value, CompilerError> {
+    let value: Option<Value> = match data_type {
+        DataType::Boolean => Some(Value::Boolean(literal.eq_ignore_ascii_case("true"))),
+        DataType::Character => literal.chars().nth(1).map(Value::Character),
+        DataType::Integer => IntegerType::I64.parse_literal(literal).map(Value::Integer),
+        DataType::SizedInteger(typ) => typ
+            .parse_literal(literal)
+            .map(|value| Value::SizedInteger(*typ, value)),
+        DataType::String => Some(Value::String(literal.trim_matches('"').to_string())),
+        DataType::Custom(_) | DataType::Array(..) | DataType::Pointer(_) => None,
+    };
+    value.ok_or_else(|| {
+        CompilerError::BytecodeError(
+            Some(location.clone()),
+            format!("Invalid literal '{literal}'"),
+        )
+    })
+}
+
+/// Encode bytecode to the binary `.rotc` format
+pub fn serialize_bytecode(bytecode: &Bytecode) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(BYTECODE_MAGIC);
+    bytes.push(BYTECODE_VERSION);
+    write_u32(&mut bytes, bytecode.entry as u32);
+
+    // Source files are stored once and referenced by index from locations
+    let mut files: Vec<&str> = Vec::new();
+    for function in &bytecode.functions {
+        for file in function.locations.iter().filter_map(|l| l.file.as_deref()) {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    write_u32(&mut bytes, files.len() as u32);
+    for file in &files {
+        write_string(&mut bytes, file);
+    }
+
+    write_u32(&mut bytes, bytecode.constants.len() as u32);
+    for constant in &bytecode.constants {
+        write_value(&mut bytes, constant);
+    }
+
+    write_u32(&mut bytes, bytecode.memory_size as u32);
+    write_u32(&mut bytes, bytecode.structs.len() as u32);
+    for structure in &bytecode.structs {
+        write_string(&mut bytes, &structure.name);
+        write_u32(&mut bytes, structure.fields.len() as u32);
+        for field in &structure.fields {
+            write_string(&mut bytes, field);
+        }
+    }
+
+    write_u32(&mut bytes, bytecode.functions.len() as u32);
+    for function in &bytecode.functions {
+        write_string(&mut bytes, &function.name);
+        write_u32(&mut bytes, function.parameters as u32);
+        write_u32(&mut bytes, function.returns as u32);
+        write_u32(&mut bytes, function.locals as u32);
+        write_u32(&mut bytes, function.instructions.len() as u32);
+        for instruction in &function.instructions {
+            write_instruction(&mut bytes, instruction);
+        }
+        for location in &function.locations {
+            write_u32(&mut bytes, location.row as u32);
+            write_u32(&mut bytes, location.column as u32);
+            let file_index: u32 = match &location.file {
+                Some(file) => files.iter().position(|f| f == file).unwrap() as u32,
+                None => NO_FILE,
+            };
+            write_u32(&mut bytes, file_index);
+        }
+    }
+    bytes
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    write_u32(bytes, string.len() as u32);
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn write_value(bytes: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Boolean(boolean) => {
+            bytes.push(0);
+            bytes.push(*boolean as u8);
+        }
+        Value::Character(character) => {
+            bytes.push(1);
+            write_u32(bytes, *character as u32);
+        }
+        Value::Integer(integer) => {
+            bytes.push(2);
+            bytes.extend_from_slice(&integer.to_le_bytes());
+        }
+        Value::String(string) => {
+            bytes.push(3);
+            write_string(bytes, string);
+        }
+        Value::Array(typ, address) => {
+            bytes.push(4);
+            write_type(bytes, typ);
+            write_u32(bytes, *address as u32);
+        }
+        Value::SizedInteger(typ, integer) => {
+            bytes.push(5);
+            write_integer_type(bytes, *typ);
+            bytes.extend_from_slice(&integer.to_le_bytes());
+        }
+        Value::Struct(..) => unreachable!("Structs are constructed at runtime"),
+        Value::Pointer(..) => unreachable!("Pointers are created at runtime"),
+    }
+}
+
+fn write_type(bytes: &mut Vec<u8>, typ: &DataType) {
+    match typ {
+        DataType::Boolean => bytes.push(0),
+        DataType::Character => bytes.push(1),
+        DataType::Integer => bytes.push(2),
+        DataType::String => bytes.push(3),
+        DataType::Custom(name) => {
+            bytes.push(4);
+            write_string(bytes, name);
+        }
+        DataType::Array(element, length) => {
+            bytes.push(5);
+            write_type(bytes, element);
+            write_u32(bytes, *length as u32);
+        }
+        DataType::Pointer(Some(pointee)) => {
+            bytes.push(6);
+            write_type(bytes, pointee);
+        }
+        DataType::Pointer(None) => bytes.push(7),
+        DataType::SizedInteger(typ) => {
+            bytes.push(8);
+            write_integer_type(bytes, *typ);
+        }
+    }
+}
+
+fn write_integer_type(bytes: &mut Vec<u8>, typ: IntegerType) {
+    bytes.push(IntegerType::iter().position(|t| t == typ).unwrap() as u8);
+}
+
+fn write_instruction(bytes: &mut Vec<u8>, instruction: &Instruction) {
+    match instruction {
+        Instruction::Push(index) => {
+            bytes.push(0);
+            write_u32(bytes, *index as u32);
+        }
+        Instruction::Call(index) => {
+            bytes.push(1);
+            write_u32(bytes, *index as u32);
+        }
+        Instruction::Intrinsic(intrinsic) => {
+            bytes.push(2);
+            bytes.push(Intrinsic::iter().position(|i| i == *intrinsic).unwrap() as u8);
+        }
+        Instruction::Binary(operator) => {
+            bytes.push(3);
+            bytes.push(BinaryOperator::iter().position(|o| o == *operator).unwrap() as u8);
+        }
+        Instruction::Jump(address) => {
+            bytes.push(4);
+            write_u32(bytes, *address as u32);
+        }
+        Instruction::JumpIfFalse(address) => {
+            bytes.push(5);
+            write_u32(bytes, *address as u32);
+        }
+        Instruction::Return => bytes.push(6),
+        Instruction::Construct(index) => {
+            bytes.push(7);
+            write_u32(bytes, *index as u32);
+        }
+        Instruction::Field(name) => {
+            bytes.push(8);
+            write_string(bytes, name);
+        }
+        Instruction::Load => bytes.push(9),
+        Instruction::Store => bytes.push(10),
+        Instruction::Cast(typ) => {
+            bytes.push(11);
+            write_type(bytes, typ);
+        }
+        Instruction::GetLocal(slot) => {
+            bytes.push(12);
+            write_u32(bytes, *slot as u32);
+        }
+        Instruction::SetLocal(slot) => {
+            bytes.push(13);
+            write_u32(bytes, *slot as u32);
+        }
+        Instruction::Unary(operator) => {
+            bytes.push(14);
+            bytes.push(UnaryOperator::iter().position(|o| o == *operator).unwrap() as u8);
+        }
+    }
+}
+
+/// Decode bytecode from the binary `.rotc` format
+pub fn deserialize_bytecode(bytes: &[u8]) -> Result<Bytecode, CompilerError> {
+    let mut reader = ByteReader { bytes, cursor: 0 };
+    if reader.read_bytes(BYTECODE_MAGIC.len())? != BYTECODE_MAGIC {
+        return Err(invalid_bytecode("Not a Rot bytecode file"));
+    }
+    let version: u8 = reader.read_u8()?;
+    if version != BYTECODE_VERSION {
+        return Err(CompilerError::BytecodeError(
+            None,
+            format!("Unsupported bytecode version {version}, expected {BYTECODE_VERSION}"),
+        ));
+    }
+    let entry: usize = reader.read_u32()? as usize;
+
+    let mut files: Vec<String> = Vec::new();
+    for _ in 0..reader.read_u32()? {
+        files.push(reader.read_string()?);
+    }
+
+    let mut constants: Vec<Value> = Vec::new();
+    for _ in 0..reader.read_u32()? {
+        constants.push(reader.read_value()?);
+    }
+
+    let memory_size: usize = reader.read_u32()? as usize;
+    if memory_size > MAX_MEMORY_SIZE {
+        return Err(invalid_bytecode("Memory size exceeds the limit"));
+    }
+    let mut structs: Vec<BytecodeStruct> = Vec::new();
+    for _ in 0..reader.read_u32()? {
+        let name: String = reader.read_string()?;
+        let mut fields: Vec<String> = Vec::new();
+        for _ in 0..reader.read_u32()? {
+            fields.push(reader.read_string()?);
+        }
+        structs.push(BytecodeStruct { name, fields });
+    }
+
+    let mut functions: Vec<BytecodeFunction> = Vec::new();
+    for _ in 0..reader.read_u32()? {
+        let name: String = reader.read_string()?;
+        let parameters: usize = reader.read_u32()? as usize;
+        let returns: usize = reader.read_u32()? as usize;
+        let locals: usize = reader.read_u32()? as usize;
+        if locals > MAX_LOCALS {
+            return Err(invalid_bytecode(
+                "Number of local variables exceeds the limit",
+            ));
+        }
+        let mut instructions: Vec<Instruction> = Vec::new();
+        for _ in 0..reader.read_u32()? {
+            instructions.push(reader.read_instruction()?);
+        }
+        let mut locations: Vec<Location> = Vec::new();
+        for _ in 0..instructions.len() {
+            let row: usize = reader.read_u32()? as usize;
+            let column: usize = reader.read_u32()? as usize;
+            let file: Option<String> = match reader.read_u32()? {
+                NO_FILE => None,
+                index => match files.get(index as usize) {
+                    Some(file) => Some(file.clone()),
+                    None => return Err(invalid_bytecode("File index out of bounds")),
+                },
+            };
+            locations.push(Location::new(row, column, file));
+        }
+        functions.push(BytecodeFunction {
+            name,
+            parameters,
+            returns,
+            locals,
+            instructions,
+            locations,
+        });
+    }
+    if reader.cursor != bytes.len() {
+        return Err(invalid_bytecode("Trailing data at the end of the file"));
+    }
+
+    let bytecode = Bytecode {
+        constants,
+        structs,
+        memory_size,
+        functions,
+        entry,
+    };
+    validate_bytecode(&bytecode)?;
+    Ok(bytecode)
+}
+
+/// Make sure that every index in the bytecode points to an existing item
+/// so that the virtual machine can execute it without further checks
+fn validate_bytecode(bytecode: &Bytecode) -> Result<(), CompilerError> {
+    if bytecode.entry >= bytecode.functions.len() {
+        return Err(invalid_bytecode("Entry function index out of bounds"));
+    }
+    for constant in &bytecode.constants {
+        if let Value::Array(typ, address) = constant {
+            if address + typ.storage_size() > bytecode.memory_size {
+                return Err(invalid_bytecode("Array address out of bounds"));
+            }
+        }
+    }
+    for function in &bytecode.functions {
+        if function.instructions.last() != Some(&Instruction::Return) {
+            return Err(invalid_bytecode("Function does not end with a return"));
+        }
+        for instruction in &function.instructions {
+            let is_valid: bool = match instruction {
+                Instruction::Push(index) => *index < bytecode.constants.len(),
+                Instruction::Call(index) => *index < bytecode.functions.len(),
+                Instruction::Construct(index) => *index < bytecode.structs.len(),
+                Instruction::GetLocal(slot) | Instruction::SetLocal(slot) => {
+                    *slot < function.locals
+                }
+                Instruction::Jump(address) | Instruction::JumpIfFalse(address) => {
+                    *address < function.instructions.len()
+                }
+                _ => true,
+            };
+            if !is_valid {
+                return Err(invalid_bytecode(&format!(
+                    "Operand out of bounds in {instruction:?} of function '{}'",
+                    function.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn invalid_bytecode(reason: &str) -> CompilerError {
+    CompilerError::BytecodeError(None, format!("Invalid bytecode file: {reason}"))
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], CompilerError> {
+        if self.cursor + count > self.bytes.len() {
+            return Err(invalid_bytecode("Unexpected end of file"));
+        }
+        let bytes: &[u8] = &self.bytes[self.cursor..self.cursor + count];
+        self.cursor += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, CompilerError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, CompilerError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_i64(&mut self) -> Result<i64, CompilerError> {
+        Ok(i64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_string(&mut self) -> Result<String, CompilerError> {
+        let length: usize = self.read_u32()? as usize;
+        String::from_utf8(self.read_bytes(length)?.to_vec())
+            .map_err(|_| invalid_bytecode("String is not valid UTF-8"))
+    }
+
+    fn read_value(&mut self) -> Result<Value, CompilerError> {
+        match self.read_u8()? {
+            0 => Ok(Value::Boolean(self.read_u8()? != 0)),
+            1 => char::from_u32(self.read_u32()?)
+                .map(Value::Character)
+                .ok_or_else(|| invalid_bytecode("Invalid character constant")),
+            2 => Ok(Value::Integer(self.read_i64()?)),
+            3 => Ok(Value::String(self.read_string()?)),
+            4 => {
+                let typ: DataType = self.read_type()?;
+                Ok(Value::Array(typ, self.read_u32()? as usize))
+            }
+            5 => {
+                let typ: IntegerType = self.read_integer_type()?;
+                let value: i64 = self.read_i64()?;
+                match typ.contains(value) {
+                    true => Ok(Value::integer(typ, value)),
+                    false => Err(invalid_bytecode("Integer constant out of range")),
+                }
+            }
+            tag => Err(CompilerError::BytecodeError(
+                None,
+                format!("Invalid bytecode file: Unknown constant tag {tag}"),
+            )),
+        }
+    }
+
+    fn read_type(&mut self) -> Result<DataType, CompilerError> {
+        // Pointers to pointers are counted first so that the type is read without recursion
+        let mut pointers: usize = 0;
+        let mut tag: u8 = self.read_u8()?;
+        while tag == 6 {
+            pointers += 1;
+            tag = self.read_u8()?;
+        }
+        let mut typ: DataType = match tag {
+            // Arrays of arrays are not supported so the element type is read without recursion
+            5 => {
+                let tag: u8 = self.read_u8()?;
+                let element: DataType = self.read_element_type(tag)?;
+                DataType::Array(Box::new(element), self.read_u32()? as usize)
+            }
+            7 => DataType::Pointer(None),
+            tag => self.read_element_type(tag)?,
+        };
+        for _ in 0..pointers {
+            typ = DataType::Pointer(Some(Box::new(typ)));
+        }
+        Ok(typ)
+    }
+
+    fn read_element_type(&mut self, tag: u8) -> Result<DataType, CompilerError> {
+        match tag {
+            0 => Ok(DataType::Boolean),
+            1 => Ok(DataType::Character),
+            2 => Ok(DataType::Integer),
+            3 => Ok(DataType::String),
+            4 => Ok(DataType::Custom(self.read_string()?)),
+            8 => Ok(DataType::of_integer(self.read_integer_type()?)),
+            tag => Err(CompilerError::BytecodeError(
+                None,
+                format!("Invalid bytecode file: Unknown type tag {tag}"),
+            )),
+        }
+    }
+
+    fn read_integer_type(&mut self) -> Result<IntegerType, CompilerError> {
+        IntegerType::iter()
+            .nth(self.read_u8()? as usize)
+            .ok_or_else(|| invalid_bytecode("Unknown integer type"))
+    }
+
+    fn read_instruction(&mut self) -> Result<Instruction, CompilerError> {
+        match self.read_u8()? {
+            0 => Ok(Instruction::Push(self.read_u32()? as usize)),
+            1 => Ok(Instruction::Call(self.read_u32()? as usize)),
+            2 => Intrinsic::iter()
+                .nth(self.read_u8()? as usize)
+                .map(Instruction::Intrinsic)
+                .ok_or_else(|| invalid_bytecode("Unknown intrinsic")),
+            3 => BinaryOperator::iter()
+                .nth(self.read_u8()? as usize)
+                .map(Instruction::Binary)
+                .ok_or_else(|| invalid_bytecode("Unknown binary operator")),
+            4 => Ok(Instruction::Jump(self.read_u32()? as usize)),
+            5 => Ok(Instruction::JumpIfFalse(self.read_u32()? as usize)),
+            6 => Ok(Instruction::Return),
+            7 => Ok(Instruction::Construct(self.read_u32()? as usize)),
+            8 => Ok(Instruction::Field(self.read_string()?)),
+            9 => Ok(Instruction::Load),
+            10 => Ok(Instruction::Store),
+            11 => Ok(Instruction::Cast(self.read_type()?)),
+            12 => Ok(Instruction::GetLocal(self.read_u32()? as usize)),
+            13 => Ok(Instruction::SetLocal(self.read_u32()? as usize)),
+            14 => UnaryOperator::iter()
+                .nth(self.read_u8()? as usize)
+                .map(Instruction::Unary)
+                .ok_or_else(|| invalid_bytecode("Unknown unary operator")),
+            opcode => Err(CompilerError::BytecodeError(
+                None,
+                format!("Invalid bytecode file: Unknown opcode {opcode}"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::TEST_FOLDER, Compiler};
+
+    fn compile_test_file(file: &str) -> Bytecode {
+        let mut compiler = Compiler::new();
+        compiler.add_file(&format!("{TEST_FOLDER}/{file}")).unwrap();
+        compiler.compile().unwrap()
+    }
+
+    #[test]
+    fn bytecode_roundtrip() {
+        for file in [
+            "vm_control_flow.rot",
+            "arrays.rot",
+            "pointers.rot",
+            "locals.rot",
+            "operators.rot",
+            "integers.rot",
+        ] {
+            let bytecode: Bytecode = compile_test_file(file);
+            let bytes: Vec<u8> = serialize_bytecode(&bytecode);
+            assert_eq!(deserialize_bytecode(&bytes).unwrap(), bytecode);
+        }
+    }
+
+    #[test]
+    fn bytecode_invalid_file() {
+        let mut bytes: Vec<u8> = serialize_bytecode(&compile_test_file("vm_arithmetic.rot"));
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            deserialize_bytecode(&bytes),
+            Err(CompilerError::BytecodeError(..))
+        ));
+        assert!(matches!(
+            deserialize_bytecode(b"not bytecode"),
+            Err(CompilerError::BytecodeError(..))
+        ));
+    }
+
+    #[test]
+    fn bytecode_size_limits() {
+        let mut bytecode: Bytecode = compile_test_file("vm_arithmetic.rot");
+        bytecode.memory_size = u32::MAX as usize;
+        assert!(matches!(
+            deserialize_bytecode(&serialize_bytecode(&bytecode)),
+            Err(CompilerError::BytecodeError(..))
+        ));
+        let mut bytecode: Bytecode = compile_test_file("vm_arithmetic.rot");
+        bytecode.functions[0].locals = u32::MAX as usize;
+        assert!(matches!(
+            deserialize_bytecode(&serialize_bytecode(&bytecode)),
+            Err(CompilerError::BytecodeError(..))
+        ));
+    }
+}
@@ -1,18 +1,58 @@
+use std::fmt;
+
+use serde::Serialize;
+
 use crate::data_types::DataType;
 
-#[derive(Debug, PartialEq)]
+use super::location::Location;
+use super::token::{BinaryOperator, UnaryOperator};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Expression {
+    #[serde(rename = "type")]
     pub typ: ExpressionType,
     pub value: Option<String>,
     pub expressions: Option<Vec<Expression>>,
+    pub location: Location,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum ExpressionType {
-    Binary,
+    /// Binary operation on the stack. `Assignment` stores the value below the element
+    /// of its only child `Index` expression to the element, or to the local variable
+    /// of its only child `Identifier` expression.
+    Binary(BinaryOperator),
+    /// Value on the top of the stack converted to the type, e.g. `cast(ptr)`
+    Cast(DataType),
+    /// Expressions between brackets
     Enclosure,
+    /// Field called `value` of the struct pushed by the only child expression
+    /// or of the struct on the top of the stack if there are no children
+    FieldAccess,
+    /// Call of the function called `value` with arguments between parentheses.
+    /// Each child is an `Enclosure` pushing one argument, e.g. `add(34, 35)`.
     FunctionCall,
     Identifier,
+    /// Element of the array pushed by the first child expression or of the array
+    /// on the top of the stack if there is only one child. The last child is
+    /// an `Enclosure` pushing the index.
+    Index,
     Literal(DataType),
-    Unary,
+    /// Operation replacing the value on the top of the stack, e.g. `not`
+    Unary(UnaryOperator),
+}
+
+impl Expression {
+    /// Write the expression and its children indented by `depth`
+    pub fn fmt_tree(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let indent: String = "  ".repeat(depth);
+        match &self.value {
+            Some(value) => writeln!(f, "{indent}{:?} {value:?} @ {}", self.typ, self.location)?,
+            None => writeln!(f, "{indent}{:?} @ {}", self.typ, self.location)?,
+        }
+        for expression in self.expressions.iter().flatten() {
+            expression.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
 }
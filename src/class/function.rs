@@ -1,13 +1,34 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::location::Location;
 use super::signature::Signature;
+use super::statement::Statement;
 use super::token::Token;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Function {
     pub name: String,
+    /// Location of the function name in its definition
+    pub location: Location,
     pub signature: Signature,
+    #[serde(skip)]
     pub tokens: Vec<Token>,
+    pub statements: Vec<Statement>,
 }
 
-pub fn function_defined(name: &str, functions: &Vec<Function>) -> bool {
+pub fn function_defined(name: &str, functions: &[Function]) -> bool {
     functions.iter().any(|function| function.name == name)
 }
+
+/// Function is displayed as its signature followed by the statement tree
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Function {} : {}", self.name, self.signature)?;
+        for statement in &self.statements {
+            statement.fmt_tree(f, 1)?;
+        }
+        Ok(())
+    }
+}
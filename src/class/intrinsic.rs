@@ -0,0 +1,111 @@
+use strum_macros::{EnumCount, EnumIter};
+
+/// Built-in words which operate directly on the stack.
+/// Bytecode refers to the intrinsics by their order so new ones are added last.
+#[derive(Debug, Clone, Copy, PartialEq, EnumCount, EnumIter)]
+pub enum Intrinsic {
+    Drop,
+    Dup,
+    Over,
+    Print,
+    Swap,
+    Assert,
+    Load8,
+    Load16,
+    Load32,
+    Load64,
+    Store8,
+    Store16,
+    Store32,
+    Store64,
+    Syscall0,
+    Syscall1,
+    Syscall2,
+    Syscall3,
+    Syscall4,
+    Syscall5,
+    Syscall6,
+    DivMod,
+}
+
+pub fn intrinsic_from_string(string: &str) -> Option<Intrinsic> {
+    match string {
+        "assert" => Some(Intrinsic::Assert),
+        "divmod" => Some(Intrinsic::DivMod),
+        "drop" => Some(Intrinsic::Drop),
+        "dup" => Some(Intrinsic::Dup),
+        "load8" => Some(Intrinsic::Load8),
+        "load16" => Some(Intrinsic::Load16),
+        "load32" => Some(Intrinsic::Load32),
+        "load64" => Some(Intrinsic::Load64),
+        "over" => Some(Intrinsic::Over),
+        "print" => Some(Intrinsic::Print),
+        "store8" => Some(Intrinsic::Store8),
+        "store16" => Some(Intrinsic::Store16),
+        "store32" => Some(Intrinsic::Store32),
+        "store64" => Some(Intrinsic::Store64),
+        "swap" => Some(Intrinsic::Swap),
+        "syscall0" => Some(Intrinsic::Syscall0),
+        "syscall1" => Some(Intrinsic::Syscall1),
+        "syscall2" => Some(Intrinsic::Syscall2),
+        "syscall3" => Some(Intrinsic::Syscall3),
+        "syscall4" => Some(Intrinsic::Syscall4),
+        "syscall5" => Some(Intrinsic::Syscall5),
+        "syscall6" => Some(Intrinsic::Syscall6),
+        _ => None,
+    }
+}
+
+impl Intrinsic {
+    /// Stack effect of the intrinsic where the letters from `a` stand for values
+    /// of any type and `n` for the number of the syscall
+    pub fn stack_effect(&self) -> &'static str {
+        match self {
+            Intrinsic::Assert => "bool ->",
+            Intrinsic::DivMod => "int int -> int int",
+            Intrinsic::Drop => "a ->",
+            Intrinsic::Dup => "a -> a a",
+            Intrinsic::Over => "a b -> a b a",
+            Intrinsic::Print => "a ->",
+            Intrinsic::Swap => "a b -> b a",
+            Intrinsic::Load8 | Intrinsic::Load16 | Intrinsic::Load32 | Intrinsic::Load64 => {
+                "ptr -> int"
+            }
+            Intrinsic::Store8 | Intrinsic::Store16 | Intrinsic::Store32 | Intrinsic::Store64 => {
+                "int ptr ->"
+            }
+            Intrinsic::Syscall0 => "n -> int",
+            Intrinsic::Syscall1 => "a n -> int",
+            Intrinsic::Syscall2 => "a b n -> int",
+            Intrinsic::Syscall3 => "a b c n -> int",
+            Intrinsic::Syscall4 => "a b c d n -> int",
+            Intrinsic::Syscall5 => "a b c d e n -> int",
+            Intrinsic::Syscall6 => "a b c d e f n -> int",
+        }
+    }
+
+    /// Number of bytes the intrinsic loads from or stores to memory
+    pub fn access_size(&self) -> Option<usize> {
+        match self {
+            Intrinsic::Load8 | Intrinsic::Store8 => Some(1),
+            Intrinsic::Load16 | Intrinsic::Store16 => Some(2),
+            Intrinsic::Load32 | Intrinsic::Store32 => Some(4),
+            Intrinsic::Load64 | Intrinsic::Store64 => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Number of arguments below the syscall number taken by the syscall intrinsics
+    pub fn syscall_arguments(&self) -> Option<usize> {
+        match self {
+            Intrinsic::Syscall0 => Some(0),
+            Intrinsic::Syscall1 => Some(1),
+            Intrinsic::Syscall2 => Some(2),
+            Intrinsic::Syscall3 => Some(3),
+            Intrinsic::Syscall4 => Some(4),
+            Intrinsic::Syscall5 => Some(5),
+            Intrinsic::Syscall6 => Some(6),
+            _ => None,
+        }
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, Clone, PartialEq)]
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Location {
     pub row: usize,
     pub column: usize,
@@ -7,10 +11,15 @@ pub struct Location {
 
 impl Location {
     pub fn new(row: usize, column: usize, file: Option<String>) -> Self {
-        Self {
-            row,
-            column,
-            file,
+        Self { row, column, file }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.row, self.column),
+            None => write!(f, "{}:{}", self.row, self.column),
         }
     }
 }
@@ -1,7 +1,9 @@
 pub mod expression;
 pub mod function;
+pub mod intrinsic;
 pub mod location;
 pub mod program;
 pub mod signature;
 pub mod statement;
+pub mod structure;
 pub mod token;
@@ -1,6 +1,103 @@
-use super::statement::Statement;
+use serde::Serialize;
 
-#[derive(Debug)]
+use crate::data_types::DataType;
+
+use super::function::Function;
+use super::location::Location;
+use super::structure::Struct;
+
+/// Top-level items of a Rot program
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Program {
-    pub statements: Vec<Statement>,
+    pub functions: Vec<Function>,
+    pub constants: Vec<Constant>,
+    pub structs: Vec<Struct>,
+    pub memories: Vec<Memory>,
+    pub includes: Vec<Include>,
+}
+
+/// Named literal value: `const <name> = <literal>`.
+/// Variants of `enum <name> { <variant>, ... }` are integer constants called `<name>.<variant>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Constant {
+    pub name: String,
+    pub location: Location,
+    #[serde(rename = "type")]
+    pub typ: DataType,
+    pub value: String,
+}
+
+/// Zero initialized storage which exists for the whole program: `memory <name>: <type>`.
+/// The name pushes the address of the storage, e.g. the array.
+#[derive(Debug, Clone, Serialize)]
+pub struct Memory {
+    pub name: String,
+    pub location: Location,
+    #[serde(rename = "type")]
+    pub typ: DataType,
+}
+
+/// Another Rot file whose items are added to the program: `include "<path>"`
+#[derive(Debug, Clone, Serialize)]
+pub struct Include {
+    pub path: String,
+    pub location: Location,
+}
+
+impl Program {
+    /// Location of the item called `name` if there is one
+    pub fn item_location(&self, name: &str) -> Option<&Location> {
+        let functions = self
+            .functions
+            .iter()
+            .map(|function| (&function.name, &function.location));
+        let constants = self
+            .constants
+            .iter()
+            .map(|constant| (&constant.name, &constant.location));
+        let structs = self
+            .structs
+            .iter()
+            .map(|structure| (&structure.name, &structure.location));
+        let memories = self
+            .memories
+            .iter()
+            .map(|memory| (&memory.name, &memory.location));
+        functions
+            .chain(constants)
+            .chain(structs)
+            .chain(memories)
+            .find(|(item_name, _)| *item_name == name)
+            .map(|(_, location)| location)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn structure(&self, name: &str) -> Option<&Struct> {
+        self.structs.iter().find(|structure| structure.name == name)
+    }
+
+    /// Memory called `name` and its offset from the start of all memories
+    pub fn memory(&self, name: &str) -> Option<(usize, &Memory)> {
+        self.memory_offsets()
+            .find(|(_, memory)| memory.name == name)
+    }
+
+    /// Size of all memories in bytes
+    pub fn memory_size(&self) -> usize {
+        self.memory_offsets()
+            .last()
+            .map_or(0, |(offset, memory)| offset + memory.typ.storage_size())
+    }
+
+    /// Memories are placed one after another aligned to 8 bytes
+    fn memory_offsets(&self) -> impl Iterator<Item = (usize, &Memory)> {
+        self.memories.iter().scan(0, |offset: &mut usize, memory| {
+            let start: usize = *offset;
+            *offset = (start + memory.typ.storage_size()).next_multiple_of(8);
+            Some((start, memory))
+        })
+    }
 }
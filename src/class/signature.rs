@@ -1,13 +1,35 @@
+use std::fmt;
+
+use serde::Serialize;
+
 use crate::data_types::DataType;
 
-#[derive(Debug, Clone)]
+use super::location::Location;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Signature {
     pub parameters: Vec<Parameter>,
     pub return_type: Vec<DataType>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Parameter {
     pub name: String,
+    #[serde(rename = "type")]
     pub typ: DataType,
+    pub location: Location,
+}
+
+/// Signature is displayed as a stack effect, e.g. `int int -> int`
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|parameter| parameter.typ.to_string())
+            .collect();
+        let return_type: Vec<String> = self.return_type.iter().map(|typ| typ.to_string()).collect();
+        let stack_effect: String = format!("{} -> {}", parameters.join(" "), return_type.join(" "));
+        write!(f, "{}", stack_effect.trim())
+    }
 }
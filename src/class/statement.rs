@@ -1,23 +1,55 @@
-use crate::data_types::DataType;
+use std::fmt;
+
+use serde::Serialize;
 
 use super::expression::Expression;
+use super::location::Location;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Statement {
+    #[serde(rename = "type")]
     pub typ: StatementType,
     pub value: Option<String>,
     pub expression: Option<Expression>,
     pub statements: Option<Vec<Statement>>,
+    pub location: Location,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum StatementType {
+    /// Statements: the `Variable`s which pop values from the stack, the last one
+    /// from the top, followed by the `Compound` body where they are in scope
+    Binding,
+    Break,
     Compound,
+    /// Statements: `[condition, body]` optionally followed by the `else` branch
+    /// which is either a `Compound` (else) or another `Conditional` (elif)
     Conditional,
+    Continue,
     Expression,
     Function,
+    /// Statements: `[condition, body]`
     Loop,
     NoOperation,
     Return,
-    Variable(DataType),
+    /// Local variable called `value`
+    Variable,
+}
+
+impl Statement {
+    /// Write the statement and its children indented by `depth`
+    pub fn fmt_tree(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let indent: String = "  ".repeat(depth);
+        match &self.value {
+            Some(value) => writeln!(f, "{indent}{:?} {value:?} @ {}", self.typ, self.location)?,
+            None => writeln!(f, "{indent}{:?} @ {}", self.typ, self.location)?,
+        }
+        if let Some(expression) = &self.expression {
+            expression.fmt_tree(f, depth + 1)?;
+        }
+        for statement in self.statements.iter().flatten() {
+            statement.fmt_tree(f, depth + 1)?;
+        }
+        Ok(())
+    }
 }
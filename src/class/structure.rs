@@ -0,0 +1,59 @@
+use serde::Serialize;
+
+use super::location::Location;
+use crate::data_types::DataType;
+
+use super::signature::{Parameter, Signature};
+
+/// Struct type: `struct <name> { <field>: <type>, ... }`.
+/// Struct values are constructed from the field values on the stack.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Struct {
+    pub name: String,
+    pub location: Location,
+    pub fields: Vec<Parameter>,
+}
+
+/// Memory layout of a struct where the fields are aligned like in C
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub size: usize,
+    pub alignment: usize,
+    /// Offset of each field in bytes from the start of the struct
+    pub offsets: Vec<usize>,
+}
+
+impl Struct {
+    pub fn field(&self, name: &str) -> Option<(usize, &Parameter)> {
+        self.fields
+            .iter()
+            .enumerate()
+            .find(|(_, field)| field.name == name)
+    }
+
+    /// Struct name constructs the struct from its fields on the stack
+    pub fn constructor_signature(&self) -> Signature {
+        Signature {
+            parameters: self.fields.clone(),
+            return_type: vec![DataType::Custom(self.name.clone())],
+        }
+    }
+
+    pub fn layout(&self) -> Layout {
+        let mut size: usize = 0;
+        let mut alignment: usize = 1;
+        let mut offsets: Vec<usize> = Vec::new();
+        for field in &self.fields {
+            let field_alignment: usize = field.typ.alignment();
+            size = size.next_multiple_of(field_alignment);
+            offsets.push(size);
+            size += field.typ.size();
+            alignment = alignment.max(field_alignment);
+        }
+        Layout {
+            size: size.next_multiple_of(alignment),
+            alignment,
+            offsets,
+        }
+    }
+}
@@ -1,28 +1,42 @@
+use std::fmt;
+
 use phf::phf_ordered_map;
+use serde::Serialize;
 use strum_macros::{EnumCount, EnumIter};
 
-use crate::data_types::DataType;
+use crate::data_types::{DataType, IntegerType};
 
 use super::location::Location;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Token {
     pub value: String,
+    #[serde(rename = "type")]
     pub typ: TokenType,
     pub location: Location,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:?} {:?}", self.location, self.typ, self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum TokenType {
     BinaryOperator(BinaryOperator),
+    UnaryOperator(UnaryOperator),
     Delimiter(Delimiter),
     Identifier,
     Literal(DataType),
     Keyword(Keyword),
-    None,
+    /// Comment which is only kept in the lossless token stream
+    Comment,
+    /// Whitespace which is only kept in the lossless token stream
+    Whitespace,
 }
 
-#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter)]
+#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter, Serialize)]
 pub enum BinaryOperator {
     Addition,
     Subtraction,
@@ -35,9 +49,24 @@ pub enum BinaryOperator {
     LessOrEqual,
     LessThan,
     NotEquals,
+    Modulo,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    /// Logical operators take both operands from the stack so they cannot short-circuit
+    And,
+    Or,
 }
 
-#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter)]
+#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter, Serialize)]
+pub enum UnaryOperator {
+    Not,
+    BitwiseNot,
+}
+
+#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter, Serialize)]
 pub enum Delimiter {
     Arrow,
     Point,
@@ -52,7 +81,7 @@ pub enum Delimiter {
     CloseCurly,
 }
 
-#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter)]
+#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter, Serialize)]
 pub enum Keyword {
     Break,
     Cast,
@@ -62,47 +91,63 @@ pub enum Keyword {
     Done,
     Elif,
     Else,
+    End,
     Endif,
     Enum,
     Fun,
     If,
+    In,
     Include,
+    Let,
     Memory,
     Return,
+    Struct,
     While,
 }
 
 pub const TOKEN_REGEXES: phf::OrderedMap<&str, TokenType> = phf_ordered_map!(
-    r"^\s+"             => TokenType::None,
+    r"^\s+"             => TokenType::Whitespace,
 
     // Comments
-    r"^//.*"            => TokenType::None, // Single-line comment
-    r"^/\*[\s\S]*?\*/"  => TokenType::None, // Multi-line comment
+    r"^//.*"            => TokenType::Comment, // Single-line comment
+    r"^/\*[\s\S]*?\*/"  => TokenType::Comment, // Multi-line comment
 
     // Literals
-    r"(?i)^true"        => TokenType::Literal(DataType::Boolean),
-    r"(?i)^false"       => TokenType::Literal(DataType::Boolean),
+    r"(?i)^true\b"      => TokenType::Literal(DataType::Boolean),
+    r"(?i)^false\b"     => TokenType::Literal(DataType::Boolean),
     r"^'[^']'"          => TokenType::Literal(DataType::Character),
+    r"^\d+i8\b"         => TokenType::Literal(DataType::SizedInteger(IntegerType::I8)),
+    r"^\d+i16\b"        => TokenType::Literal(DataType::SizedInteger(IntegerType::I16)),
+    r"^\d+i32\b"        => TokenType::Literal(DataType::SizedInteger(IntegerType::I32)),
+    r"^\d+i64\b"        => TokenType::Literal(DataType::Integer),
+    r"^\d+u8\b"         => TokenType::Literal(DataType::SizedInteger(IntegerType::U8)),
+    r"^\d+u16\b"        => TokenType::Literal(DataType::SizedInteger(IntegerType::U16)),
+    r"^\d+u32\b"        => TokenType::Literal(DataType::SizedInteger(IntegerType::U32)),
+    r"^\d+u64\b"        => TokenType::Literal(DataType::SizedInteger(IntegerType::U64)),
     r"^\d+"             => TokenType::Literal(DataType::Integer),
     r#"^"[^"]*""#       => TokenType::Literal(DataType::String),
 
     // Keywords
-    r"^break"           => TokenType::Keyword(Keyword::Break),
-    r"^cast"            => TokenType::Keyword(Keyword::Cast),
-    r"^const"           => TokenType::Keyword(Keyword::Const),
-    r"^continue"        => TokenType::Keyword(Keyword::Continue),
-    r"^done"            => TokenType::Keyword(Keyword::Done),
-    r"^do"              => TokenType::Keyword(Keyword::Do),
-    r"^elif"            => TokenType::Keyword(Keyword::Elif),
-    r"^else"            => TokenType::Keyword(Keyword::Else),
-    r"^endif"           => TokenType::Keyword(Keyword::Endif),
-    r"^enum"            => TokenType::Keyword(Keyword::Enum),
-    r"^fun"             => TokenType::Keyword(Keyword::Fun),
-    r"^if"              => TokenType::Keyword(Keyword::If),
-    r"^include"         => TokenType::Keyword(Keyword::Include),
-    r"^memory"          => TokenType::Keyword(Keyword::Memory),
-    r"^return"          => TokenType::Keyword(Keyword::Return),
-    r"^while"           => TokenType::Keyword(Keyword::While),
+    r"^break\b"         => TokenType::Keyword(Keyword::Break),
+    r"^cast\b"          => TokenType::Keyword(Keyword::Cast),
+    r"^const\b"         => TokenType::Keyword(Keyword::Const),
+    r"^continue\b"      => TokenType::Keyword(Keyword::Continue),
+    r"^done\b"          => TokenType::Keyword(Keyword::Done),
+    r"^do\b"            => TokenType::Keyword(Keyword::Do),
+    r"^elif\b"          => TokenType::Keyword(Keyword::Elif),
+    r"^else\b"          => TokenType::Keyword(Keyword::Else),
+    r"^end\b"           => TokenType::Keyword(Keyword::End),
+    r"^endif\b"         => TokenType::Keyword(Keyword::Endif),
+    r"^enum\b"          => TokenType::Keyword(Keyword::Enum),
+    r"^fun\b"           => TokenType::Keyword(Keyword::Fun),
+    r"^if\b"            => TokenType::Keyword(Keyword::If),
+    r"^in\b"            => TokenType::Keyword(Keyword::In),
+    r"^include\b"       => TokenType::Keyword(Keyword::Include),
+    r"^let\b"           => TokenType::Keyword(Keyword::Let),
+    r"^memory\b"        => TokenType::Keyword(Keyword::Memory),
+    r"^return\b"        => TokenType::Keyword(Keyword::Return),
+    r"^struct\b"        => TokenType::Keyword(Keyword::Struct),
+    r"^while\b"         => TokenType::Keyword(Keyword::While),
 
     // Delimiters
     r"^\("              => TokenType::Delimiter(Delimiter::OpenParen),
@@ -118,9 +163,13 @@ pub const TOKEN_REGEXES: phf::OrderedMap<&str, TokenType> = phf_ordered_map!(
     r"^;"               => TokenType::Delimiter(Delimiter::SemiColon),
 
     // Binary Operators
+    r"^and\b"           => TokenType::BinaryOperator(BinaryOperator::And),
+    r"^or\b"            => TokenType::BinaryOperator(BinaryOperator::Or),
     r"^=="              => TokenType::BinaryOperator(BinaryOperator::Equals),
+    r"^>>"              => TokenType::BinaryOperator(BinaryOperator::ShiftRight),
     r"^>="              => TokenType::BinaryOperator(BinaryOperator::GreaterOrEqual),
     r"^>"               => TokenType::BinaryOperator(BinaryOperator::GreaterThan),
+    r"^<<"              => TokenType::BinaryOperator(BinaryOperator::ShiftLeft),
     r"^<="              => TokenType::BinaryOperator(BinaryOperator::LessOrEqual),
     r"^<"               => TokenType::BinaryOperator(BinaryOperator::LessThan),
     r"^!="              => TokenType::BinaryOperator(BinaryOperator::NotEquals),
@@ -129,6 +178,14 @@ pub const TOKEN_REGEXES: phf::OrderedMap<&str, TokenType> = phf_ordered_map!(
     r"^/"               => TokenType::BinaryOperator(BinaryOperator::Division),
     r"^\*"              => TokenType::BinaryOperator(BinaryOperator::Multiplication),
     r"^-"               => TokenType::BinaryOperator(BinaryOperator::Subtraction),
+    r"^%"               => TokenType::BinaryOperator(BinaryOperator::Modulo),
+    r"^&"               => TokenType::BinaryOperator(BinaryOperator::BitwiseAnd),
+    r"^\|"              => TokenType::BinaryOperator(BinaryOperator::BitwiseOr),
+    r"^\^"              => TokenType::BinaryOperator(BinaryOperator::BitwiseXor),
+
+    // Unary Operators
+    r"^not\b"           => TokenType::UnaryOperator(UnaryOperator::Not),
+    r"^~"               => TokenType::UnaryOperator(UnaryOperator::BitwiseNot),
 
     // Identifier - Named value representing some value or other entity
     r"^[a-zA-Z_$][a-zA-Z_$0-9]*" => TokenType::Identifier,
@@ -1,17 +1,34 @@
 use clap::{Args, Parser, Subcommand};
 
+use crate::driver::{Emit, OutputFormat};
+
 /// Rot compiler
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
 pub struct RotArgs {
     #[command(subcommand)]
     pub action: CliAction,
+    /// Output format of the emitted tokens and AST and the diagnostics: text or json
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    pub format: OutputFormat,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum CliAction {
     /// Compile a Rot program
     Compile(CompilationTarget),
+    /// Run a Rot program
+    Run(RunTarget),
+    /// Check a Rot program for errors without compiling it
+    Check(CheckTarget),
+    /// Run the test functions of a Rot program
+    Test(CheckTarget),
+    /// Format Rot code files in place
+    Fmt(FormatTarget),
+    /// Start an interactive Rot session
+    Repl,
+    /// Start a Language Server Protocol server communicating over stdio
+    Lsp,
 }
 
 #[derive(Debug, Args)]
@@ -19,7 +36,7 @@ pub struct CompilationTarget {
     /// Rot code file
     pub rot_file: String,
     /// Output file
-    #[arg(short, long, value_name="FILE")]
+    #[arg(short, long, value_name = "FILE")]
     pub out: Option<String>,
     /// Save the generated assembly file
     #[arg(short, long)]
@@ -27,4 +44,45 @@ pub struct CompilationTarget {
     /// Output compilation steps
     #[arg(short, long)]
     pub verbose: bool,
+    /// Output compilation stages: tokens, ast, ir, asm, obj or exe.
+    /// Optionally followed by `=FILE` where `-` means stdout.
+    #[arg(long, value_name = "STAGE[=FILE]", value_delimiter = ',')]
+    pub emit: Vec<Emit>,
+    /// Compile to a bytecode file (.rotc) which can be run with `rot run --vm`
+    #[arg(short, long)]
+    pub bytecode: bool,
+    /// Check the indices of arrays at runtime
+    #[arg(long)]
+    pub debug: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct CheckTarget {
+    /// Rot code file
+    pub rot_file: String,
+}
+
+#[derive(Debug, Args)]
+pub struct FormatTarget {
+    /// Rot code files
+    #[arg(required = true)]
+    pub rot_files: Vec<String>,
+    /// Fail if a file is not formatted instead of rewriting it
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RunTarget {
+    /// Rot code file or compiled bytecode file (.rotc)
+    pub rot_file: String,
+    /// Execute the program with the bytecode virtual machine
+    #[arg(long)]
+    pub vm: bool,
+    /// Check the indices of arrays at runtime. The virtual machine always checks them.
+    #[arg(long)]
+    pub debug: bool,
+    /// Arguments passed to the program
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub args: Vec<String>,
 }
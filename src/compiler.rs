@@ -1,137 +1,533 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::class::expression::{Expression, ExpressionType};
 use crate::class::function::{function_defined, Function};
+use crate::class::location::Location;
+use crate::class::program::{Constant, Include, Memory, Program};
 use crate::class::signature::{Parameter, Signature};
-use crate::class::token::{Delimiter, Keyword, Token, TokenType};
+use crate::class::statement::{Statement, StatementType};
+use crate::class::structure::Struct;
+use crate::class::token::{BinaryOperator, Delimiter, Keyword, Token, TokenType, TOKEN_REGEXES};
 use crate::constant::MAIN_FUNCTION_NAME;
 use crate::data_types::{datatype_from_string, DataType};
-use crate::lexer::tokenize_code_file;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum CompilerError {
     IOError(std::io::Error),
-    ParserError(String),
+    /// Errors have the location in the code they point to, if any, and the message
+    LexerError(Option<Location>, String),
+    ParserError(Option<Location>, String),
+    TypeError(Option<Location>, String),
+    BytecodeError(Option<Location>, String),
+    CodegenError(Option<Location>, String),
+    RuntimeError(Option<Location>, String),
+    FormatError(Option<Location>, String),
+    /// Diagnostic which does not stop the compilation
+    Warning(Option<Location>, String),
 }
 
-pub fn compile_rot_file(
-    rot_file: &str,
-    _out_file: Option<String>,
-) -> Result<(), CompilerError> {
-    let tokens: Vec<Token> = tokenize_code_file(&rot_file)?;
-    let functions: Vec<Function> = parse_functions(tokens)?;
-    // TODO: Generate abstract syntax tree (AST)
-    // TODO: Generate assembly code
-    // TODO: Compile the program
+impl fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (title, location, message) = match self {
+            CompilerError::IOError(error) => return write!(f, "IO error: {error}"),
+            CompilerError::LexerError(location, message) => ("Lexer error", location, message),
+            CompilerError::ParserError(location, message) => ("Parser error", location, message),
+            CompilerError::TypeError(location, message) => ("Type error", location, message),
+            CompilerError::BytecodeError(location, message) => {
+                ("Bytecode error", location, message)
+            }
+            CompilerError::CodegenError(location, message) => {
+                ("Code generation error", location, message)
+            }
+            CompilerError::RuntimeError(location, message) => ("Runtime error", location, message),
+            CompilerError::FormatError(location, message) => ("Format error", location, message),
+            CompilerError::Warning(location, message) => ("Warning", location, message),
+        };
+        match location {
+            Some(location) => write!(f, "{title}: {location}: {message}"),
+            None => write!(f, "{title}: {message}"),
+        }
+    }
+}
+
+impl CompilerError {
+    /// Process exit code for the error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            // The program could not be read
+            CompilerError::IOError(_) => 2,
+            CompilerError::Warning(..) => 0,
+            _ => 1,
+        }
+    }
+
+    /// Machine-readable name of the error kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CompilerError::IOError(_) => "io",
+            CompilerError::LexerError(..) => "lexer",
+            CompilerError::ParserError(..) => "parser",
+            CompilerError::TypeError(..) => "type",
+            CompilerError::BytecodeError(..) => "bytecode",
+            CompilerError::CodegenError(..) => "codegen",
+            CompilerError::RuntimeError(..) => "runtime",
+            CompilerError::FormatError(..) => "format",
+            CompilerError::Warning(..) => "warning",
+        }
+    }
+
+    /// Location of the error in the code, if any, and the message without it
+    pub fn located_message(&self) -> (Option<Location>, String) {
+        match self {
+            CompilerError::IOError(error) => (None, error.to_string()),
+            CompilerError::LexerError(location, message)
+            | CompilerError::ParserError(location, message)
+            | CompilerError::TypeError(location, message)
+            | CompilerError::BytecodeError(location, message)
+            | CompilerError::CodegenError(location, message)
+            | CompilerError::RuntimeError(location, message)
+            | CompilerError::FormatError(location, message)
+            | CompilerError::Warning(location, message) => (location.clone(), message.clone()),
+        }
+    }
+}
+
+/// Errors are serialized as diagnostics with the kind, location and message
+impl Serialize for CompilerError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (location, message) = self.located_message();
+        let mut diagnostic = serializer.serialize_struct("Diagnostic", 3)?;
+        diagnostic.serialize_field("kind", self.kind())?;
+        diagnostic.serialize_field("location", &location)?;
+        diagnostic.serialize_field("message", &message)?;
+        diagnostic.end()
+    }
+}
+
+pub fn check_main_defined(functions: &[Function]) -> Result<(), CompilerError> {
+    if !function_defined(MAIN_FUNCTION_NAME, functions) {
+        return Err(CompilerError::ParserError(
+            None,
+            format!("The '{MAIN_FUNCTION_NAME}' function is not defined"),
+        ));
+    }
     Ok(())
 }
 
-fn parse_functions(tokens: Vec<Token>) -> Result<Vec<Function>, CompilerError> {
-    let mut functions: Vec<Function> = Vec::new();
-    for (i, token) in tokens.iter().enumerate() {
-        if token.typ == TokenType::Keyword(Keyword::Fun) {
-            if i >= tokens.len() - 1 {
-                return Err(CompilerError::ParserError(format!(
-                    "Code cannot end with '{}' keyword",
-                    token.value
-                )));
+/// Parse the items of the code without requiring the `main` function.
+/// Includes are only resolved by the [`Compiler`] which knows the file of the code.
+pub fn parse_tokens(tokens: &[Token]) -> Result<Program, CompilerError> {
+    let mut program: Program = parse_program(tokens)?;
+    if let Some(include) = program.includes.first() {
+        return Err(CompilerError::ParserError(
+            Some(include.location.clone()),
+            format!("Cannot include '{}' outside of a file", include.path),
+        ));
+    }
+    resolve_constants(&mut program);
+    Ok(program)
+}
+
+/// Parse the top-level items of the code in a single pass
+pub fn parse_program(tokens: &[Token]) -> Result<Program, CompilerError> {
+    let mut program = Program::default();
+    let mut names: HashMap<String, Location> = HashMap::new();
+    let mut cursor: usize = 0;
+    while cursor < tokens.len() {
+        let token: &Token = &tokens[cursor];
+        cursor += 1;
+        let keyword: &Keyword = match &token.typ {
+            TokenType::Keyword(
+                keyword @ (Keyword::Fun
+                | Keyword::Const
+                | Keyword::Enum
+                | Keyword::Struct
+                | Keyword::Memory
+                | Keyword::Include),
+            ) => keyword,
+            _ => {
+                return Err(CompilerError::ParserError(
+                    Some(token.location.clone()),
+                    format!(
+                        "Expected an item (fun, const, enum, struct, memory or include) \
+                        but got '{}'",
+                        token.value
+                    ),
+                ))
+            }
+        };
+        if cursor >= tokens.len() {
+            return Err(CompilerError::ParserError(
+                Some(token.location.clone()),
+                format!("Code cannot end with '{}' keyword", token.value),
+            ));
+        }
+        match keyword {
+            Keyword::Fun => {
+                let function: Function = parse_function(&mut cursor, tokens)?;
+                add_function(&mut program, &mut names, function)?
+            }
+            Keyword::Const => {
+                let constant: Constant = parse_constant(&mut cursor, tokens)?;
+                add_constant(&mut program, &mut names, constant)?
+            }
+            Keyword::Enum => {
+                for constant in parse_enum(&mut cursor, tokens)? {
+                    add_constant(&mut program, &mut names, constant)?;
+                }
             }
-            functions.push(parse_function(tokens[i + 1..].to_vec())?);
+            Keyword::Struct => {
+                let structure: Struct = parse_struct(&mut cursor, tokens)?;
+                add_struct(&mut program, &mut names, structure)?
+            }
+            Keyword::Memory => {
+                let memory: Memory = parse_memory(&mut cursor, tokens)?;
+                add_memory(&mut program, &mut names, memory)?
+            }
+            Keyword::Include => program.includes.push(Include {
+                path: parse_include(&mut cursor, tokens)?,
+                location: token.location.clone(),
+            }),
+            _ => unreachable!("Only item keywords are matched"),
         }
     }
-    if !function_defined(MAIN_FUNCTION_NAME, &functions) {
-        panic!("The '{}' function is not defined", MAIN_FUNCTION_NAME);
+    Ok(program)
+}
+
+/// Add the items of another program, e.g. from an included file.
+/// `names` holds the locations of the items already in the program.
+pub fn merge_programs(
+    program: &mut Program,
+    names: &mut HashMap<String, Location>,
+    other: Program,
+) -> Result<(), CompilerError> {
+    for function in other.functions {
+        add_function(program, names, function)?;
+    }
+    for constant in other.constants {
+        add_constant(program, names, constant)?;
+    }
+    for structure in other.structs {
+        add_struct(program, names, structure)?;
+    }
+    for memory in other.memories {
+        add_memory(program, names, memory)?;
     }
-    Ok(functions)
+    program.includes.extend(other.includes);
+    Ok(())
 }
 
-/// In Rot, function is defined with the following syntax:
-/// function <name>(param1: int, param2: str) -> bool { <code> }
-fn parse_function(tokens: Vec<Token>) -> Result<Function, CompilerError> {
-    let mut cursor: usize = 0;
-    let name: String = tokens[0].value.clone();
-    advance_cursor(&mut cursor, &tokens, TokenType::Identifier)?;
-    let signature: Signature = parse_function_signature(&mut cursor, &tokens)?;
-    advance_cursor(
-        &mut cursor,
-        &tokens,
-        TokenType::Delimiter(Delimiter::OpenCurly),
-    )?;
+fn add_function(
+    program: &mut Program,
+    names: &mut HashMap<String, Location>,
+    function: Function,
+) -> Result<(), CompilerError> {
+    define_name(names, "Function", &function.name, &function.location)?;
+    program.functions.push(function);
+    Ok(())
+}
 
-    // Parse until the closing curly brace
-    let mut found_close_curly: bool = false;
-    let mut function_tokens: Vec<Token> = Vec::new();
-    while cursor < tokens.len() {
-        found_close_curly = advance_cursor(
-            &mut cursor,
-            &tokens,
-            TokenType::Delimiter(Delimiter::CloseCurly),
-        )
-        .is_ok();
-        if found_close_curly {
-            break;
+fn add_constant(
+    program: &mut Program,
+    names: &mut HashMap<String, Location>,
+    constant: Constant,
+) -> Result<(), CompilerError> {
+    define_name(names, "Constant", &constant.name, &constant.location)?;
+    program.constants.push(constant);
+    Ok(())
+}
+
+fn add_struct(
+    program: &mut Program,
+    names: &mut HashMap<String, Location>,
+    structure: Struct,
+) -> Result<(), CompilerError> {
+    define_name(names, "Struct", &structure.name, &structure.location)?;
+    program.structs.push(structure);
+    Ok(())
+}
+
+fn add_memory(
+    program: &mut Program,
+    names: &mut HashMap<String, Location>,
+    memory: Memory,
+) -> Result<(), CompilerError> {
+    define_name(names, "Memory", &memory.name, &memory.location)?;
+    program.memories.push(memory);
+    Ok(())
+}
+
+/// All items share the same names as they are all used as words.
+/// The names are looked up in a map so that adding an item does not scan the program.
+fn define_name(
+    names: &mut HashMap<String, Location>,
+    kind: &str,
+    name: &str,
+    location: &Location,
+) -> Result<(), CompilerError> {
+    match names.entry(name.to_string()) {
+        Entry::Occupied(defined) => Err(CompilerError::ParserError(
+            Some(location.clone()),
+            format!("{kind} '{name}' is already defined at {}", defined.get()),
+        )),
+        Entry::Vacant(entry) => {
+            entry.insert(location.clone());
+            Ok(())
         }
-        function_tokens.push(tokens[cursor - 1].clone());
     }
-    if !found_close_curly {
+}
+
+/// Replace the names of the constants in the function bodies with their values
+pub fn resolve_constants(program: &mut Program) {
+    for function in &mut program.functions {
+        for statement in &mut function.statements {
+            resolve_statement_constants(statement, &program.constants);
+        }
+    }
+}
+
+fn resolve_statement_constants(statement: &mut Statement, constants: &[Constant]) {
+    if let Some(expression) = &mut statement.expression {
+        resolve_expression_constants(expression, constants);
+    }
+    for statement in statement.statements.iter_mut().flatten() {
+        resolve_statement_constants(statement, constants);
+    }
+}
+
+fn resolve_expression_constants(expression: &mut Expression, constants: &[Constant]) {
+    for expression in expression.expressions.iter_mut().flatten() {
+        resolve_expression_constants(expression, constants);
+    }
+    let name: String = match (&expression.typ, expression.expressions.as_deref()) {
+        (ExpressionType::Identifier, _) => expression.value.clone().unwrap_or_default(),
+        // Variants of enums are accessed like fields, e.g. `Color.Red`
+        (ExpressionType::FieldAccess, Some([item])) if item.typ == ExpressionType::Identifier => {
+            format!(
+                "{}.{}",
+                item.value.as_deref().unwrap_or_default(),
+                expression.value.as_deref().unwrap_or_default()
+            )
+        }
+        _ => return,
+    };
+    if let Some(constant) = constants.iter().find(|constant| constant.name == name) {
+        expression.typ = ExpressionType::Literal(constant.typ.clone());
+        expression.value = Some(constant.value.clone());
+        expression.expressions = None;
+    }
+}
+
+/// const <name> = <literal>
+fn parse_constant(cursor: &mut usize, tokens: &[Token]) -> Result<Constant, CompilerError> {
+    let name_token: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+    advance_cursor(
+        cursor,
+        tokens,
+        TokenType::BinaryOperator(BinaryOperator::Assignment),
+    )?;
+    let value_token: Token = match tokens.get(*cursor) {
+        Some(token) => token.clone(),
+        None => {
+            return Err(CompilerError::ParserError(
+                Some(name_token.location.clone()),
+                format!(
+                    "Unexpected EOF while parsing constant '{}'",
+                    name_token.value
+                ),
+            ))
+        }
+    };
+    *cursor += 1;
+    let TokenType::Literal(typ) = value_token.typ else {
         return Err(CompilerError::ParserError(
-            "Unexpected EOF while parsing a function".to_string(),
+            Some(value_token.location.clone()),
+            format!(
+                "Expected a literal value for constant '{}' but got '{}'",
+                name_token.value, value_token.value
+            ),
         ));
+    };
+    Ok(Constant {
+        name: name_token.value,
+        location: name_token.location,
+        typ,
+        value: value_token.value,
+    })
+}
+
+/// enum <name> { <variant>, <variant>, ... }
+/// Variants are numbered from zero in the order they are declared.
+fn parse_enum(cursor: &mut usize, tokens: &[Token]) -> Result<Vec<Constant>, CompilerError> {
+    let name_token: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+    let open_curly: Token =
+        advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::OpenCurly))?;
+    let mut variants: Vec<Constant> = Vec::new();
+    loop {
+        let Some(token) = tokens.get(*cursor) else {
+            return Err(CompilerError::ParserError(
+                Some(open_curly.location.clone()),
+                "Unclosed '{' opened here".to_string(),
+            ));
+        };
+        if token.typ == TokenType::Delimiter(Delimiter::CloseCurly) {
+            *cursor += 1;
+            break;
+        }
+        let variant: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+        variants.push(Constant {
+            name: format!("{}.{}", name_token.value, variant.value),
+            location: variant.location,
+            typ: DataType::Integer,
+            value: variants.len().to_string(),
+        });
+        let is_comma = |token: &Token| token.typ == TokenType::Delimiter(Delimiter::Comma);
+        if tokens.get(*cursor).is_some_and(is_comma) {
+            *cursor += 1;
+        }
     }
+    Ok(variants)
+}
+
+/// struct <name> { <field>: <type>, ... }
+fn parse_struct(cursor: &mut usize, tokens: &[Token]) -> Result<Struct, CompilerError> {
+    let name_token: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+    advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::OpenCurly))?;
+    let fields: Vec<Parameter> = parse_parameters(cursor, tokens, Delimiter::CloseCurly)?;
+    advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::CloseCurly))?;
+    Ok(Struct {
+        name: name_token.value,
+        location: name_token.location,
+        fields,
+    })
+}
+
+/// memory <name>: <type>
+fn parse_memory(cursor: &mut usize, tokens: &[Token]) -> Result<Memory, CompilerError> {
+    let name_token: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+    advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::Colon))?;
+    Ok(Memory {
+        name: name_token.value,
+        location: name_token.location,
+        typ: parse_type(cursor, tokens)?,
+    })
+}
+
+/// include "<path>"
+fn parse_include(cursor: &mut usize, tokens: &[Token]) -> Result<String, CompilerError> {
+    let path: Token = advance_cursor(cursor, tokens, TokenType::Literal(DataType::String))?;
+    Ok(path.value.trim_matches('"').to_string())
+}
+
+/// In Rot, function is defined with the following syntax:
+/// fun <name>(param1: int, param2: str) -> bool { <code> }
+fn parse_function(cursor: &mut usize, tokens: &[Token]) -> Result<Function, CompilerError> {
+    let name_token: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+    let signature: Signature = parse_function_signature(cursor, tokens)?;
+    let open_curly: Token =
+        advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::OpenCurly))?;
+
+    let body_start: usize = *cursor;
+    let body: Statement = parse_braced_block(cursor, tokens, open_curly.location)?;
+    // The closing brace is not part of the body
+    let body_end: usize = *cursor - 1;
+    let statements: Vec<Statement> = body.statements.unwrap_or_default();
 
     Ok(Function {
-        name,
+        name: name_token.value,
+        location: name_token.location,
         signature,
-        tokens: function_tokens,
+        tokens: tokens[body_start..body_end].to_vec(),
+        statements,
     })
 }
 
 fn advance_cursor(
     cursor: &mut usize,
-    tokens: &Vec<Token>,
+    tokens: &[Token],
     expected_type: TokenType,
 ) -> Result<Token, CompilerError> {
-    if *cursor >= tokens.len() {
-        // TODO: Enhance error reporting
-        return Err(CompilerError::ParserError(
-            "Unexpected EOF while parsing".to_string(),
+    let Some(token) = tokens.get(*cursor) else {
+        return Err(unexpected_eof(
+            tokens,
+            &format!("Expected {}", describe_token_type(&expected_type)),
         ));
-    }
-    let token: &Token = &tokens[*cursor];
+    };
     *cursor += 1;
     if token.typ != expected_type {
-        return Err(CompilerError::ParserError(format!(
-            "Expected TokenType {:?} but got {:?}\n{:?}",
-            expected_type, token.typ, token
-        )));
+        return Err(CompilerError::ParserError(
+            Some(token.location.clone()),
+            format!(
+                "Expected {} but got '{}'",
+                describe_token_type(&expected_type),
+                token.value
+            ),
+        ));
     }
     Ok(token.clone())
 }
 
+/// Error for code which ends before `expected`, located at the last token
+fn unexpected_eof(tokens: &[Token], expected: &str) -> CompilerError {
+    CompilerError::ParserError(
+        tokens.last().map(|token| token.location.clone()),
+        format!("{expected} but got the end of the code"),
+    )
+}
+
+/// Token type as it is named in the parser errors, e.g. `an identifier` or `'{'`
+fn describe_token_type(typ: &TokenType) -> String {
+    match typ {
+        TokenType::Identifier => "an identifier".to_string(),
+        TokenType::Literal(typ) => format!("a literal of type {typ}"),
+        // Other tokens have a fixed text which is the pattern of their regex, e.g. `^\{`
+        _ => match TOKEN_REGEXES
+            .entries()
+            .find(|(_, regex_type)| *regex_type == typ)
+        {
+            Some((regex, _)) => {
+                let text: &str = regex.trim_start_matches('^').trim_end_matches(r"\b");
+                format!("'{}'", text.replace('\\', ""))
+            }
+            None => format!("{typ:?}"),
+        },
+    }
+}
+
 fn parse_function_signature(
     cursor: &mut usize,
-    tokens: &Vec<Token>,
+    tokens: &[Token],
 ) -> Result<Signature, CompilerError> {
     let mut return_type: Vec<DataType> = Vec::new();
     advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::OpenParen))?;
-    let parameters: Vec<Parameter> = parse_function_parameters(cursor, tokens)?;
+    let parameters: Vec<Parameter> = parse_parameters(cursor, tokens, Delimiter::CloseParen)?;
     advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::CloseParen))?;
 
     // -> indicates that function has a return value
     // { indicates that function does not return anything
-    match &tokens[*cursor].typ {
+    let Some(token) = tokens.get(*cursor) else {
+        return Err(unexpected_eof(tokens, "Expected '->' or '{'"));
+    };
+    match &token.typ {
         TokenType::Delimiter(delimiter) => match delimiter {
             Delimiter::OpenCurly => {}
             Delimiter::Arrow => {
                 advance_cursor(cursor, tokens, TokenType::Delimiter(delimiter.clone()))?;
-                return_type.push(datatype_from_string(
-                    &advance_cursor(cursor, tokens, TokenType::Identifier)?.value,
-                ))
+                return_type.push(parse_type(cursor, tokens)?)
             }
             _ => {}
         },
         _ => {
-            return Err(CompilerError::ParserError(format!(
-                "Expected '->' or '{{' but got '{}'",
-                tokens[*cursor].value
-            )))
+            return Err(CompilerError::ParserError(
+                Some(token.location.clone()),
+                format!("Expected '->' or '{{' but got '{}'", token.value),
+            ))
         }
     }
     Ok(Signature {
@@ -140,30 +536,883 @@ fn parse_function_signature(
     })
 }
 
-fn parse_function_parameters(
+/// Name of the type optionally followed by the lengths of arrays, e.g. `int[16]`.
+/// Pointers can name the type they point to, e.g. `ptr<char>`.
+fn parse_type(cursor: &mut usize, tokens: &[Token]) -> Result<DataType, CompilerError> {
+    let mut is_closed: bool = false;
+    let typ: DataType = parse_nested_type(cursor, tokens, &mut is_closed)?;
+    if is_closed {
+        // Second half of the `>>` closes nothing
+        let location: &Location = &tokens[*cursor - 1].location;
+        let location = Location::new(location.row, location.column + 1, location.file.clone());
+        return Err(CompilerError::ParserError(
+            Some(location.clone()),
+            "Unexpected '>' after the type".to_string(),
+        ));
+    }
+    Ok(typ)
+}
+
+/// Nested pointer types can end with `>>` which is lexed as a shift, e.g. `ptr<ptr<int>>`.
+/// The inner type consumes it and sets `is_closed` to close the outer type too.
+fn parse_nested_type(
+    cursor: &mut usize,
+    tokens: &[Token],
+    is_closed: &mut bool,
+) -> Result<DataType, CompilerError> {
+    let name: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+    let mut typ: DataType = datatype_from_string(&name.value);
+    let less_than = TokenType::BinaryOperator(BinaryOperator::LessThan);
+    if typ == DataType::Pointer(None)
+        && tokens
+            .get(*cursor)
+            .is_some_and(|token| token.typ == less_than)
+    {
+        *cursor += 1;
+        let pointee: DataType = parse_nested_type(cursor, tokens, is_closed)?;
+        let shift_right = TokenType::BinaryOperator(BinaryOperator::ShiftRight);
+        if *is_closed {
+            *is_closed = false;
+        } else if tokens
+            .get(*cursor)
+            .is_some_and(|token| token.typ == shift_right)
+        {
+            *cursor += 1;
+            *is_closed = true;
+        } else {
+            advance_cursor(
+                cursor,
+                tokens,
+                TokenType::BinaryOperator(BinaryOperator::GreaterThan),
+            )?;
+        }
+        typ = DataType::Pointer(Some(Box::new(pointee)));
+    }
+    let is_open_square = |token: &Token| token.typ == TokenType::Delimiter(Delimiter::OpenSquare);
+    // Lengths after `>>` belong to the outer type
+    while !*is_closed && tokens.get(*cursor).is_some_and(is_open_square) {
+        *cursor += 1;
+        let length: Token = advance_cursor(cursor, tokens, TokenType::Literal(DataType::Integer))?;
+        advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::CloseSquare))?;
+        let length: usize = match length.value.parse::<usize>() {
+            Ok(length) if length > 0 => length,
+            _ => {
+                return Err(CompilerError::ParserError(
+                    Some(length.location.clone()),
+                    format!(
+                        "Length of the array should be a positive number but got '{}'",
+                        length.value
+                    ),
+                ))
+            }
+        };
+        typ = DataType::Array(Box::new(typ), length);
+    }
+    Ok(typ)
+}
+
+/// Parse comma separated `<name>: <type>` pairs until the closing delimiter,
+/// e.g. the parameters of a function or the fields of a struct
+fn parse_parameters(
     cursor: &mut usize,
-    tokens: &Vec<Token>,
+    tokens: &[Token],
+    closing: Delimiter,
 ) -> Result<Vec<Parameter>, CompilerError> {
     let mut parameters: Vec<Parameter> = Vec::new();
 
     loop {
         if *cursor >= tokens.len() {
-            return Err(CompilerError::ParserError(
-                "Unexpected EOF while parsing function parameters".to_string(),
+            let closing: TokenType = TokenType::Delimiter(closing);
+            return Err(unexpected_eof(
+                tokens,
+                &format!("Expected {}", describe_token_type(&closing)),
             ));
         }
-        if tokens[*cursor].typ == TokenType::Delimiter(Delimiter::CloseParen) {
+        if tokens[*cursor].typ == TokenType::Delimiter(closing.clone()) {
             break;
         }
-        let name: String = advance_cursor(cursor, tokens, TokenType::Identifier)?.value;
+        let name: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
         advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::Colon))?;
-        let typ =
-            datatype_from_string(&advance_cursor(cursor, tokens, TokenType::Identifier)?.value);
-        parameters.push(Parameter { name, typ });
-        if tokens[*cursor].typ != TokenType::Delimiter(Delimiter::Comma) {
+        let typ: DataType = parse_type(cursor, tokens)?;
+        parameters.push(Parameter {
+            name: name.value,
+            typ,
+            location: name.location,
+        });
+        let is_comma = |token: &Token| token.typ == TokenType::Delimiter(Delimiter::Comma);
+        if !tokens.get(*cursor).is_some_and(is_comma) {
             break;
         }
         *cursor += 1;
     }
     Ok(parameters)
 }
+
+/// Parse statements until one of the `terminators` or the end of tokens.
+/// The terminating token is left for the caller to consume.
+pub fn parse_statements(
+    cursor: &mut usize,
+    tokens: &[Token],
+    terminators: &[TokenType],
+) -> Result<Vec<Statement>, CompilerError> {
+    let mut statements: Vec<Statement> = Vec::new();
+    while *cursor < tokens.len() && !terminators.contains(&tokens[*cursor].typ) {
+        statements.push(parse_statement(cursor, tokens)?);
+    }
+    Ok(statements)
+}
+
+fn parse_statement(cursor: &mut usize, tokens: &[Token]) -> Result<Statement, CompilerError> {
+    let token: Token = tokens[*cursor].clone();
+    *cursor += 1;
+    let expression_type: ExpressionType = match &token.typ {
+        TokenType::Literal(data_type) => ExpressionType::Literal(data_type.clone()),
+        TokenType::BinaryOperator(operator) => ExpressionType::Binary(operator.clone()),
+        TokenType::UnaryOperator(operator) => ExpressionType::Unary(operator.clone()),
+        TokenType::Identifier => ExpressionType::Identifier,
+        TokenType::Keyword(Keyword::If) => {
+            return parse_conditional(cursor, tokens, token.location)
+        }
+        TokenType::Keyword(Keyword::While) => return parse_loop(cursor, tokens, token.location),
+        TokenType::Keyword(Keyword::Let) => return parse_binding(cursor, tokens, token.location),
+        TokenType::Delimiter(Delimiter::OpenCurly) => {
+            return parse_braced_block(cursor, tokens, token.location)
+        }
+        TokenType::Delimiter(Delimiter::Point | Delimiter::OpenSquare) => {
+            *cursor -= 1;
+            let expression: Expression = parse_accesses(cursor, tokens, None)?;
+            return Ok(expression_statement(expression));
+        }
+        TokenType::Keyword(Keyword::Cast) => {
+            let expression: Expression = parse_cast(cursor, tokens, token.location)?;
+            return Ok(expression_statement(expression));
+        }
+        TokenType::Keyword(Keyword::Break) => {
+            return Ok(new_statement(StatementType::Break, None, token.location))
+        }
+        TokenType::Keyword(Keyword::Continue) => {
+            return Ok(new_statement(StatementType::Continue, None, token.location))
+        }
+        TokenType::Keyword(Keyword::Return) => {
+            return Ok(new_statement(StatementType::Return, None, token.location))
+        }
+        _ => {
+            return Err(CompilerError::ParserError(
+                Some(token.location.clone()),
+                format!("Unexpected token '{}'", token.value),
+            ))
+        }
+    };
+    let expression = Expression {
+        typ: expression_type,
+        value: Some(token.value),
+        expressions: None,
+        location: token.location.clone(),
+    };
+    let open_paren = TokenType::Delimiter(Delimiter::OpenParen);
+    let expression: Expression = match tokens.get(*cursor) {
+        Some(next) if expression.typ == ExpressionType::Identifier && next.typ == open_paren => {
+            *cursor += 1;
+            parse_call(cursor, tokens, expression)?
+        }
+        _ => expression,
+    };
+    // Fields and elements can be accessed directly from the word pushing the value,
+    // e.g. `point.x` and `values[2]`
+    let expression: Expression = match expression.typ {
+        ExpressionType::Identifier | ExpressionType::FunctionCall => {
+            parse_accesses(cursor, tokens, Some(expression))?
+        }
+        _ => expression,
+    };
+    Ok(expression_statement(expression))
+}
+
+fn expression_statement(expression: Expression) -> Statement {
+    Statement {
+        typ: StatementType::Expression,
+        value: None,
+        location: expression.location.clone(),
+        expression: Some(expression),
+        statements: None,
+    }
+}
+
+/// .<field> and [<index>] accesses in any order, e.g. `.items[0].x`
+/// Each access takes the value from the previous expression or from the stack if there is none.
+/// The last element can be assigned the value below it with `=`, e.g. `5 values[2] =`,
+/// and so can a word without accesses, e.g. `5 x =`.
+fn parse_accesses(
+    cursor: &mut usize,
+    tokens: &[Token],
+    mut expression: Option<Expression>,
+) -> Result<Expression, CompilerError> {
+    while let Some(token) = tokens.get(*cursor) {
+        let access: Expression = match token.typ {
+            TokenType::Delimiter(Delimiter::Point) => {
+                *cursor += 1;
+                let field: Token = advance_cursor(cursor, tokens, TokenType::Identifier)?;
+                Expression {
+                    typ: ExpressionType::FieldAccess,
+                    value: Some(field.value),
+                    expressions: expression.map(|expression| vec![expression]),
+                    location: field.location,
+                }
+            }
+            TokenType::Delimiter(Delimiter::OpenSquare) => {
+                *cursor += 1;
+                let index: Expression = parse_index(cursor, tokens, token.location.clone())?;
+                Expression {
+                    typ: ExpressionType::Index,
+                    value: None,
+                    expressions: Some(expression.into_iter().chain([index]).collect()),
+                    location: token.location.clone(),
+                }
+            }
+            _ => break,
+        };
+        expression = Some(access);
+    }
+    let expression: Expression = expression.unwrap();
+    let assignment = TokenType::BinaryOperator(BinaryOperator::Assignment);
+    match tokens.get(*cursor) {
+        Some(token)
+            if matches!(
+                expression.typ,
+                ExpressionType::Index | ExpressionType::Identifier
+            ) && token.typ == assignment =>
+        {
+            *cursor += 1;
+            Ok(Expression {
+                typ: ExpressionType::Binary(BinaryOperator::Assignment),
+                value: Some(token.value.clone()),
+                expressions: Some(vec![expression]),
+                location: token.location.clone(),
+            })
+        }
+        _ => Ok(expression),
+    }
+}
+
+/// cast(<type>)
+fn parse_cast(
+    cursor: &mut usize,
+    tokens: &[Token],
+    location: Location,
+) -> Result<Expression, CompilerError> {
+    advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::OpenParen))?;
+    let typ: DataType = parse_type(cursor, tokens)?;
+    advance_cursor(cursor, tokens, TokenType::Delimiter(Delimiter::CloseParen))?;
+    Ok(Expression {
+        typ: ExpressionType::Cast(typ),
+        value: None,
+        expressions: None,
+        location,
+    })
+}
+
+/// [<expression>...]
+/// The opening bracket has already been consumed.
+fn parse_index(
+    cursor: &mut usize,
+    tokens: &[Token],
+    location: Location,
+) -> Result<Expression, CompilerError> {
+    let close_square = TokenType::Delimiter(Delimiter::CloseSquare);
+    let statements: Vec<Statement> = parse_statements(cursor, tokens, &[close_square])?;
+    if *cursor >= tokens.len() {
+        return Err(CompilerError::ParserError(
+            Some(location.clone()),
+            "Unclosed '[' opened here".to_string(),
+        ));
+    }
+    *cursor += 1;
+    if statements.is_empty() {
+        return Err(CompilerError::ParserError(
+            Some(location.clone()),
+            "Expected an index between the brackets".to_string(),
+        ));
+    }
+    enclosure(statements, location, "Index")
+}
+
+/// <function>(<argument>, ...)
+/// Each argument is an `Enclosure` of the expressions pushing its value,
+/// e.g. `add(34, x 1 +)` pushes 34 and `x 1 +` before calling `add`.
+/// The opening parenthesis has already been consumed.
+fn parse_call(
+    cursor: &mut usize,
+    tokens: &[Token],
+    function: Expression,
+) -> Result<Expression, CompilerError> {
+    let comma = TokenType::Delimiter(Delimiter::Comma);
+    let close_paren = TokenType::Delimiter(Delimiter::CloseParen);
+    let location: &Location = &function.location;
+    let mut arguments: Vec<Expression> = Vec::new();
+    if tokens
+        .get(*cursor)
+        .is_some_and(|token| token.typ == close_paren)
+    {
+        *cursor += 1;
+    } else {
+        loop {
+            let statements: Vec<Statement> =
+                parse_statements(cursor, tokens, &[comma.clone(), close_paren.clone()])?;
+            let Some(separator) = tokens.get(*cursor) else {
+                return Err(CompilerError::ParserError(
+                    Some(location.clone()),
+                    format!(
+                        "Unclosed '(' of the call to '{}'",
+                        function.value.as_deref().unwrap_or_default()
+                    ),
+                ));
+            };
+            let Some(first) = statements.first() else {
+                return Err(CompilerError::ParserError(
+                    Some(separator.location.clone()),
+                    format!("Expected an argument before '{}'", separator.value),
+                ));
+            };
+            let argument_location: Location = first.location.clone();
+            arguments.push(enclosure(statements, argument_location, "Argument")?);
+            *cursor += 1;
+            if separator.typ == close_paren {
+                break;
+            }
+        }
+    }
+    Ok(Expression {
+        typ: ExpressionType::FunctionCall,
+        expressions: Some(arguments),
+        ..function
+    })
+}
+
+/// Enclosure of the expressions of the statements. `name` describes the enclosure in errors.
+fn enclosure(
+    statements: Vec<Statement>,
+    location: Location,
+    name: &str,
+) -> Result<Expression, CompilerError> {
+    let mut expressions: Vec<Expression> = Vec::new();
+    for statement in statements {
+        match statement.expression {
+            Some(expression) if statement.typ == StatementType::Expression => {
+                expressions.push(expression)
+            }
+            _ => {
+                return Err(CompilerError::ParserError(
+                    Some(statement.location.clone()),
+                    format!("{name} can only contain expressions"),
+                ))
+            }
+        }
+    }
+    Ok(Expression {
+        typ: ExpressionType::Enclosure,
+        value: None,
+        expressions: Some(expressions),
+        location,
+    })
+}
+
+/// if <condition> do <body> [elif <condition> do <body>]* [else <body>] endif
+fn parse_conditional(
+    cursor: &mut usize,
+    tokens: &[Token],
+    location: Location,
+) -> Result<Statement, CompilerError> {
+    let condition: Statement = parse_block(cursor, tokens, &[TokenType::Keyword(Keyword::Do)])?;
+    advance_cursor(cursor, tokens, TokenType::Keyword(Keyword::Do))?;
+    let body: Statement = parse_block(
+        cursor,
+        tokens,
+        &[
+            TokenType::Keyword(Keyword::Elif),
+            TokenType::Keyword(Keyword::Else),
+            TokenType::Keyword(Keyword::Endif),
+        ],
+    )?;
+    let mut branches: Vec<Statement> = vec![condition, body];
+
+    if *cursor >= tokens.len() {
+        return Err(CompilerError::ParserError(
+            Some(location.clone()),
+            "Unexpected EOF while parsing 'if'".to_string(),
+        ));
+    }
+    let token: Token = tokens[*cursor].clone();
+    *cursor += 1;
+    match token.typ {
+        TokenType::Keyword(Keyword::Elif) => {
+            branches.push(parse_conditional(cursor, tokens, token.location)?)
+        }
+        TokenType::Keyword(Keyword::Else) => {
+            branches.push(parse_block(
+                cursor,
+                tokens,
+                &[TokenType::Keyword(Keyword::Endif)],
+            )?);
+            advance_cursor(cursor, tokens, TokenType::Keyword(Keyword::Endif))?;
+        }
+        _ => {}
+    }
+    Ok(new_statement(
+        StatementType::Conditional,
+        Some(branches),
+        location,
+    ))
+}
+
+/// while <condition> do <body> done
+fn parse_loop(
+    cursor: &mut usize,
+    tokens: &[Token],
+    location: Location,
+) -> Result<Statement, CompilerError> {
+    let condition: Statement = parse_block(cursor, tokens, &[TokenType::Keyword(Keyword::Do)])?;
+    advance_cursor(cursor, tokens, TokenType::Keyword(Keyword::Do))?;
+    let body: Statement = parse_block(cursor, tokens, &[TokenType::Keyword(Keyword::Done)])?;
+    advance_cursor(cursor, tokens, TokenType::Keyword(Keyword::Done))?;
+    Ok(new_statement(
+        StatementType::Loop,
+        Some(vec![condition, body]),
+        location,
+    ))
+}
+
+/// let <name>... in <body> end
+fn parse_binding(
+    cursor: &mut usize,
+    tokens: &[Token],
+    location: Location,
+) -> Result<Statement, CompilerError> {
+    let mut statements: Vec<Statement> = Vec::new();
+    while let Some(token) = tokens
+        .get(*cursor)
+        .filter(|token| token.typ == TokenType::Identifier)
+    {
+        *cursor += 1;
+        statements.push(Statement {
+            typ: StatementType::Variable,
+            value: Some(token.value.clone()),
+            expression: None,
+            statements: None,
+            location: token.location.clone(),
+        });
+    }
+    if statements.is_empty() {
+        return Err(CompilerError::ParserError(
+            Some(location.clone()),
+            "Expected names of the local variables after 'let'".to_string(),
+        ));
+    }
+    advance_cursor(cursor, tokens, TokenType::Keyword(Keyword::In))?;
+    statements.push(parse_block(
+        cursor,
+        tokens,
+        &[TokenType::Keyword(Keyword::End)],
+    )?);
+    advance_cursor(cursor, tokens, TokenType::Keyword(Keyword::End))?;
+    Ok(new_statement(
+        StatementType::Binding,
+        Some(statements),
+        location,
+    ))
+}
+
+/// Parse statements until one of the `terminators` into a Compound statement
+fn parse_block(
+    cursor: &mut usize,
+    tokens: &[Token],
+    terminators: &[TokenType],
+) -> Result<Statement, CompilerError> {
+    let location: Location = match tokens.get(*cursor).or(tokens.last()) {
+        Some(token) => token.location.clone(),
+        None => Location::new(1, 1, None),
+    };
+    let statements: Vec<Statement> = parse_statements(cursor, tokens, terminators)?;
+    Ok(new_statement(
+        StatementType::Compound,
+        Some(statements),
+        location,
+    ))
+}
+
+/// { <body> }
+/// Nested braces are parsed recursively, so the block ends at its matching brace
+fn parse_braced_block(
+    cursor: &mut usize,
+    tokens: &[Token],
+    location: Location,
+) -> Result<Statement, CompilerError> {
+    let statements: Vec<Statement> = parse_statements(
+        cursor,
+        tokens,
+        &[TokenType::Delimiter(Delimiter::CloseCurly)],
+    )?;
+    if *cursor >= tokens.len() {
+        return Err(CompilerError::ParserError(
+            Some(location.clone()),
+            "Unclosed '{' opened here".to_string(),
+        ));
+    }
+    *cursor += 1;
+    Ok(new_statement(
+        StatementType::Compound,
+        Some(statements),
+        location,
+    ))
+}
+
+fn new_statement(
+    typ: StatementType,
+    statements: Option<Vec<Statement>>,
+    location: Location,
+) -> Statement {
+    Statement {
+        typ,
+        value: None,
+        expression: None,
+        statements,
+        location,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::constant::TEST_FOLDER;
+    use crate::lexer::tokenize_code_file;
+    use crate::Compiler;
+
+    #[test]
+    fn json_diagnostics() {
+        let rot_file: String = format!("{TEST_FOLDER}/type_check_errors.rot");
+        let errors: Vec<CompilerError> = Compiler::new().add_file(&rot_file).unwrap().check();
+        assert_eq!(
+            serde_json::to_value(&errors[0]).unwrap(),
+            json!({
+                "kind": "type",
+                "location": { "row": 2, "column": 12, "file": rot_file },
+                "message": "Invalid argument types for '+': int bool",
+            })
+        );
+        // Errors without a location in the code
+        let error = CompilerError::RuntimeError(None, "Division by zero".to_string());
+        assert_eq!(
+            error.located_message(),
+            (None, "Division by zero".to_string())
+        );
+        // Paths and messages with colons do not change the location
+        let location = Location::new(3, 7, Some("C:/rot/main.rot".to_string()));
+        let message: String = "Expected bool: got int".to_string();
+        let error = CompilerError::TypeError(Some(location.clone()), message.clone());
+        assert_eq!(
+            error.to_string(),
+            "Type error: C:/rot/main.rot:3:7: Expected bool: got int"
+        );
+        assert_eq!(error.located_message(), (Some(location), message));
+    }
+
+    #[test]
+    fn parse_function_errors() {
+        let parse_test_file = |file: &str| {
+            let mut compiler = Compiler::new();
+            compiler.add_file(&format!("{TEST_FOLDER}/{file}")).unwrap();
+            compiler.parse().unwrap_err().to_string()
+        };
+        assert_eq!(
+            parse_test_file("parse_duplicate_functions.rot"),
+            format!(
+                "Parser error: {TEST_FOLDER}/parse_duplicate_functions.rot:9:5: Function 'helper' is already defined at {TEST_FOLDER}/parse_duplicate_functions.rot:5:5"
+            )
+        );
+        assert_eq!(
+            parse_test_file("parse_unclosed_brace.rot"),
+            format!("Parser error: {TEST_FOLDER}/parse_unclosed_brace.rot:5:16: Unclosed '{{' opened here")
+        );
+        assert_eq!(
+            parse_test_file("parse_expected_item.rot"),
+            format!("Parser error: {TEST_FOLDER}/parse_expected_item.rot:5:1: Expected an item (fun, const, enum, struct, memory or include) but got '2'")
+        );
+        assert_eq!(
+            parse_test_file("program_without_main_function.rot"),
+            "Parser error: The 'main' function is not defined"
+        );
+
+        // Unexpected tokens and the end of the code are located
+        let parse_code = |code: &str| {
+            let tokens: Vec<Token> = crate::lexer::tokenize_code(code, None).unwrap();
+            parse_tokens(&tokens).unwrap_err().to_string()
+        };
+        assert_eq!(
+            parse_code("fun main( { }"),
+            "Parser error: 1:11: Expected an identifier but got '{'"
+        );
+        assert_eq!(
+            parse_code("fun main(a: int"),
+            "Parser error: 1:13: Expected ')' but got the end of the code"
+        );
+        assert_eq!(
+            parse_code("fun main() {}\nfun"),
+            "Parser error: 2:1: Code cannot end with 'fun' keyword"
+        );
+    }
+
+    #[test]
+    fn parse_program_items() {
+        let tokens: Vec<Token> =
+            tokenize_code_file(&format!("{TEST_FOLDER}/parse_items.rot")).unwrap();
+        let mut program: Program = parse_program(&tokens).unwrap();
+        assert_eq!(program.includes[0].path, "parse_items_include.rot");
+        let constants: Vec<(&str, &str)> = program
+            .constants
+            .iter()
+            .map(|constant| (constant.name.as_str(), constant.value.as_str()))
+            .collect();
+        assert_eq!(
+            constants,
+            vec![
+                ("GREETING", "\"hello\""),
+                ("LIMIT", "3"),
+                ("Color.Red", "0"),
+                ("Color.Green", "1"),
+                ("Color.Blue", "2")
+            ]
+        );
+        resolve_constants(&mut program);
+        let expression: &Expression = program.functions[0].statements[2]
+            .expression
+            .as_ref()
+            .unwrap();
+        assert_eq!(expression.typ, ExpressionType::Literal(DataType::Integer));
+        assert_eq!(expression.value.as_deref(), Some("2"));
+
+        let code: &str = "const LIMIT = 1\nfun LIMIT() {}";
+        assert_eq!(
+            parse_tokens(&crate::lexer::tokenize_code(code, None).unwrap())
+                .unwrap_err()
+                .to_string(),
+            "Parser error: 2:5: Function 'LIMIT' is already defined at 1:7"
+        );
+    }
+
+    #[test]
+    fn parse_indices() {
+        let parse_main = |code: &str| {
+            let tokens: Vec<Token> = crate::lexer::tokenize_code(code, None).unwrap();
+            parse_tokens(&tokens).map(|mut program| program.functions.remove(0).statements)
+        };
+        let statements: Vec<Statement> =
+            parse_main("fun main() { 1 values[0 1 +] = [2] }").unwrap();
+        let assignment: &Expression = statements[1].expression.as_ref().unwrap();
+        assert_eq!(
+            assignment.typ,
+            ExpressionType::Binary(BinaryOperator::Assignment)
+        );
+        let index: &Expression = &assignment.expressions.as_ref().unwrap()[0];
+        assert_eq!(index.typ, ExpressionType::Index);
+        let children: &Vec<Expression> = index.expressions.as_ref().unwrap();
+        assert_eq!(children[0].value.as_deref(), Some("values"));
+        assert_eq!(children[1].typ, ExpressionType::Enclosure);
+        assert_eq!(children[1].expressions.as_ref().unwrap().len(), 3);
+        // Index without an array takes it from the stack
+        let index: &Expression = statements[2].expression.as_ref().unwrap();
+        assert_eq!(index.expressions.as_ref().unwrap().len(), 1);
+
+        let error = |code: &str| parse_main(code).unwrap_err().to_string();
+        assert_eq!(
+            error("fun main() { values[0"),
+            "Parser error: 1:20: Unclosed '[' opened here"
+        );
+        assert_eq!(
+            error("fun main() { values[] }"),
+            "Parser error: 1:20: Expected an index between the brackets"
+        );
+        assert_eq!(
+            error("fun main() { values[if true do endif] }"),
+            "Parser error: 1:21: Index can only contain expressions"
+        );
+    }
+
+    #[test]
+    fn parse_pointer_types() {
+        let tokens: Vec<Token> =
+            crate::lexer::tokenize_code("fun f(p: ptr<ptr<char>>) -> ptr { p cast(ptr) }", None)
+                .unwrap();
+        let function: Function = parse_tokens(&tokens).unwrap().functions.remove(0);
+        let char_pointer = DataType::Pointer(Some(Box::new(DataType::Character)));
+        assert_eq!(
+            function.signature.parameters[0].typ,
+            DataType::Pointer(Some(Box::new(char_pointer)))
+        );
+        assert_eq!(
+            function.signature.return_type,
+            vec![DataType::Pointer(None)]
+        );
+        let cast: &Expression = function.statements[1].expression.as_ref().unwrap();
+        assert_eq!(cast.typ, ExpressionType::Cast(DataType::Pointer(None)));
+
+        // Closing `>>` and `>>>` are lexed as shifts and close several pointer types
+        let parse_type_code = |code: &str| {
+            let tokens: Vec<Token> = crate::lexer::tokenize_code(code, None).unwrap();
+            parse_type(&mut 0, &tokens)
+        };
+        let pointer = |typ: DataType| DataType::Pointer(Some(Box::new(typ)));
+        assert_eq!(
+            parse_type_code("ptr<ptr<ptr<int>>>").unwrap(),
+            pointer(pointer(pointer(DataType::Integer)))
+        );
+        assert_eq!(
+            parse_type_code("ptr<ptr<int>>[2]").unwrap(),
+            DataType::Array(Box::new(pointer(pointer(DataType::Integer))), 2)
+        );
+        assert_eq!(
+            parse_type_code("ptr<int>>").unwrap_err().to_string(),
+            "Parser error: 1:9: Unexpected '>' after the type"
+        );
+    }
+
+    #[test]
+    fn parse_bindings() {
+        let tokens: Vec<Token> =
+            crate::lexer::tokenize_code("fun f() { 1 2 let a b in b a = end }", None).unwrap();
+        let function: Function = parse_tokens(&tokens).unwrap().functions.remove(0);
+        let binding: &Statement = &function.statements[2];
+        assert_eq!(binding.typ, StatementType::Binding);
+        let statements: &[Statement] = binding.statements.as_deref().unwrap();
+        let names: Vec<Option<&str>> = statements[..2]
+            .iter()
+            .map(|variable| variable.value.as_deref())
+            .collect();
+        assert_eq!(names, vec![Some("a"), Some("b")]);
+        assert!(statements[..2]
+            .iter()
+            .all(|variable| variable.typ == StatementType::Variable));
+        assert_eq!(statements[2].typ, StatementType::Compound);
+        // Assignment to a word takes the word as its only child
+        let assignment: &Expression = statements[2].statements.as_ref().unwrap()[1]
+            .expression
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            assignment.typ,
+            ExpressionType::Binary(BinaryOperator::Assignment)
+        );
+        assert_eq!(
+            assignment.expressions.as_ref().unwrap()[0].value.as_deref(),
+            Some("a")
+        );
+
+        let tokens: Vec<Token> =
+            crate::lexer::tokenize_code("fun f() { 1 let in end }", None).unwrap();
+        assert_eq!(
+            parse_tokens(&tokens).unwrap_err().to_string(),
+            "Parser error: 1:13: Expected names of the local variables after 'let'"
+        );
+    }
+
+    #[test]
+    fn parse_calls() {
+        let parse_main = |code: &str| {
+            let tokens: Vec<Token> = crate::lexer::tokenize_code(code, None).unwrap();
+            parse_tokens(&tokens).map(|mut program| program.functions.remove(0).statements)
+        };
+        let statements: Vec<Statement> =
+            parse_main("fun main() { add(34, x 1 +) origin().x f() }").unwrap();
+        let call: &Expression = statements[0].expression.as_ref().unwrap();
+        assert_eq!(call.typ, ExpressionType::FunctionCall);
+        assert_eq!(call.value.as_deref(), Some("add"));
+        let arguments: &Vec<Expression> = call.expressions.as_ref().unwrap();
+        assert!(arguments
+            .iter()
+            .all(|argument| argument.typ == ExpressionType::Enclosure));
+        let lengths: Vec<usize> = arguments
+            .iter()
+            .map(|argument| argument.expressions.as_ref().unwrap().len())
+            .collect();
+        assert_eq!(lengths, vec![1, 3]);
+        assert_eq!(arguments[1].location, Location::new(1, 22, None));
+        // Results of calls can be accessed like words
+        let field: &Expression = statements[1].expression.as_ref().unwrap();
+        assert_eq!(field.typ, ExpressionType::FieldAccess);
+        assert_eq!(
+            field.expressions.as_ref().unwrap()[0].typ,
+            ExpressionType::FunctionCall
+        );
+        let call: &Expression = statements[2].expression.as_ref().unwrap();
+        assert_eq!(call.expressions.as_ref().map(Vec::len), Some(0));
+
+        let error = |code: &str| parse_main(code).unwrap_err().to_string();
+        assert_eq!(
+            error("fun main() { add(1 2"),
+            "Parser error: 1:14: Unclosed '(' of the call to 'add'"
+        );
+        assert_eq!(
+            error("fun main() { add(1, ) }"),
+            "Parser error: 1:21: Expected an argument before ')'"
+        );
+        assert_eq!(
+            error("fun main() { add(if true do 1 endif) }"),
+            "Parser error: 1:18: Argument can only contain expressions"
+        );
+    }
+
+    #[test]
+    fn parse_nested_blocks() {
+        let file: String = format!("{TEST_FOLDER}/parse_nested_blocks.rot");
+        let functions: Vec<Function> = parse_tokens(&tokenize_code_file(&file).unwrap())
+            .unwrap()
+            .functions;
+        let names: Vec<&str> = functions
+            .iter()
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main", "after"]);
+        let block: &Statement = &functions[0].statements[1];
+        assert_eq!(block.typ, StatementType::Compound);
+        assert_eq!(block.location, Location::new(2, 7, Some(file)));
+        assert_eq!(block.statements.as_ref().unwrap().len(), 3);
+        // Body ends at the brace matching the opening brace of the function
+        assert_eq!(functions[1].tokens.len(), 6);
+    }
+
+    #[test]
+    fn parse_many_functions() {
+        let function_tokens = |count: usize| {
+            let code: String = (0..count)
+                .map(|i| format!("fun function_{i}(n: int) -> int {{ {i} + }}\n"))
+                .collect();
+            crate::lexer::tokenize_code(&code, None).unwrap()
+        };
+        let tokens: Vec<Token> = function_tokens(5000);
+        let functions: Vec<Function> = parse_tokens(&tokens).unwrap().functions;
+        assert_eq!(functions.len(), 5000);
+        assert_eq!(functions[4999].name, "function_4999");
+        assert_eq!(functions[4999].tokens.len(), 2);
+
+        // Parsing time grows linearly with the number of functions. The fastest
+        // of a few runs is compared to keep the test stable on a busy machine.
+        let parse_time = |tokens: &[Token]| {
+            (0..3)
+                .map(|_| {
+                    let start: Instant = Instant::now();
+                    parse_tokens(tokens).unwrap();
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let small: Duration = parse_time(&tokens);
+        let large: Duration = parse_time(&function_tokens(20000));
+        assert!(
+            large < small * 10,
+            "Parsing 4 times more functions took {large:?} instead of about 4 times {small:?}"
+        );
+    }
+}
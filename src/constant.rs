@@ -1,2 +1,3 @@
 pub const MAIN_FUNCTION_NAME: &str = "main";
+#[cfg(test)]
 pub const TEST_FOLDER: &str = "tests";
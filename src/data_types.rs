@@ -1,20 +1,202 @@
-use strum_macros::{EnumCount, EnumIter};
+use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, EnumCount, EnumIter)]
+use serde::Serialize;
+use strum_macros::EnumIter;
+
+#[derive(Debug, Clone, PartialEq, EnumIter, Serialize)]
 pub enum DataType {
     Boolean,
     Character,
     Integer,
     String,
     Custom(String),
+    /// Fixed number of elements of the type stored one after another: `<type>[<length>]`.
+    /// Arrays are left out of the iteration as there are no array literals.
+    #[strum(disabled)]
+    Array(Box<DataType>, usize),
+    /// Address of a value in memory: `ptr` or `ptr<type>` if the type of the value is known
+    #[strum(disabled)]
+    Pointer(Option<Box<DataType>>),
+    /// Integer of the width other than `int`, e.g. `u8`.
+    /// Left out of the iteration as the literals are the ones of int with a suffix.
+    #[strum(disabled)]
+    SizedInteger(IntegerType),
+}
+
+/// Width and signedness of an integer. `int` is the same as `i64`.
+/// Values are kept sign or zero extended to 64 bits on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Serialize)]
+pub enum IntegerType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
 }
 
 pub fn datatype_from_string(string: &str) -> DataType {
     match string {
-        "bool"  => DataType::Boolean,
-        "char"  => DataType::Character,
-        "int"   => DataType::Integer,
-        "str"   => DataType::String,
-        _       => DataType::Custom(string.to_string()),
+        "bool" => DataType::Boolean,
+        "char" => DataType::Character,
+        "int" => DataType::Integer,
+        "i8" => DataType::SizedInteger(IntegerType::I8),
+        "i16" => DataType::SizedInteger(IntegerType::I16),
+        "i32" => DataType::SizedInteger(IntegerType::I32),
+        "i64" => DataType::Integer,
+        "u8" => DataType::SizedInteger(IntegerType::U8),
+        "u16" => DataType::SizedInteger(IntegerType::U16),
+        "u32" => DataType::SizedInteger(IntegerType::U32),
+        "u64" => DataType::SizedInteger(IntegerType::U64),
+        "str" => DataType::String,
+        "ptr" => DataType::Pointer(None),
+        _ => DataType::Custom(string.to_string()),
+    }
+}
+
+impl DataType {
+    /// Size of the value in bytes when it is stored in memory.
+    /// Strings, structs and arrays are stored as pointers.
+    pub fn size(&self) -> usize {
+        match self {
+            DataType::Boolean => 1,
+            DataType::Character => 4,
+            DataType::Integer => 8,
+            DataType::String => 8,
+            DataType::Custom(_) => 8,
+            DataType::Array(..) => 8,
+            DataType::Pointer(_) => 8,
+            DataType::SizedInteger(typ) => typ.size(),
+        }
+    }
+
+    /// Integer of the type with its width and signedness
+    pub fn of_integer(typ: IntegerType) -> DataType {
+        match typ {
+            IntegerType::I64 => DataType::Integer,
+            typ => DataType::SizedInteger(typ),
+        }
+    }
+
+    /// Width and signedness of the integer types
+    pub fn integer_type(&self) -> Option<IntegerType> {
+        match self {
+            DataType::Integer => Some(IntegerType::I64),
+            DataType::SizedInteger(typ) => Some(*typ),
+            _ => None,
+        }
+    }
+
+    /// Size of the storage the value refers to, e.g. all the elements of an array
+    pub fn storage_size(&self) -> usize {
+        match self {
+            DataType::Array(element, length) => element.size() * length,
+            typ => typ.size(),
+        }
+    }
+
+    /// Type of the value loaded from the pointer by a load of `size` bytes.
+    /// Pointers to bool, char, integers or pointers of that size load their type and others int.
+    pub fn loaded_type(&self, size: usize) -> DataType {
+        match self {
+            DataType::Pointer(Some(pointee)) if pointee.size() == size => match **pointee {
+                DataType::Boolean
+                | DataType::Character
+                | DataType::Integer
+                | DataType::SizedInteger(_)
+                | DataType::Pointer(_) => *pointee.clone(),
+                _ => DataType::Integer,
+            },
+            _ => DataType::Integer,
+        }
+    }
+
+    /// Values are stored at addresses which are multiples of their alignment
+    pub fn alignment(&self) -> usize {
+        self.size()
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataType::Boolean => write!(f, "bool"),
+            DataType::Character => write!(f, "char"),
+            DataType::Integer => write!(f, "int"),
+            DataType::String => write!(f, "str"),
+            DataType::Custom(name) => write!(f, "{name}"),
+            DataType::Array(element, length) => write!(f, "{element}[{length}]"),
+            DataType::Pointer(None) => write!(f, "ptr"),
+            DataType::Pointer(Some(pointee)) => write!(f, "ptr<{pointee}>"),
+            DataType::SizedInteger(typ) => write!(f, "{typ}"),
+        }
+    }
+}
+
+impl IntegerType {
+    pub fn size(self) -> usize {
+        match self {
+            IntegerType::I8 | IntegerType::U8 => 1,
+            IntegerType::I16 | IntegerType::U16 => 2,
+            IntegerType::I32 | IntegerType::U32 => 4,
+            IntegerType::I64 | IntegerType::U64 => 8,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            IntegerType::I8 | IntegerType::I16 | IntegerType::I32 | IntegerType::I64
+        )
+    }
+
+    /// Arithmetic on `i8`, `i16` and `i32` fails when the result does not fit in the type.
+    /// Arithmetic on the unsigned integers and `int` wraps around like it has always done.
+    pub fn is_checked(self) -> bool {
+        matches!(self, IntegerType::I8 | IntegerType::I16 | IntegerType::I32)
+    }
+
+    fn bits(self) -> u32 {
+        self.size() as u32 * 8
+    }
+
+    /// Smallest and largest values of the type
+    pub fn range(self) -> (i128, i128) {
+        match self.is_signed() {
+            true => (-(1 << (self.bits() - 1)), (1 << (self.bits() - 1)) - 1),
+            false => (0, (1 << self.bits()) - 1),
+        }
+    }
+
+    /// Lowest bits of the value sign or zero extended to 64 bits
+    pub fn wrap(self, value: i64) -> i64 {
+        let shift: u32 = 64 - self.bits();
+        match self.is_signed() {
+            true => (value << shift) >> shift,
+            false => ((value as u64) << shift >> shift) as i64,
+        }
+    }
+
+    /// Whether the 64 bits hold a value of the type. Values of `u64` are all the bit patterns.
+    pub fn contains(self, value: i64) -> bool {
+        self == IntegerType::U64 || self.wrap(value) == value
+    }
+
+    /// Value of the integer literal with an optional type suffix, e.g. `255u8`,
+    /// or `None` if it does not fit in the type
+    pub fn parse_literal(self, literal: &str) -> Option<i64> {
+        let digits: &str = literal.split(['i', 'u']).next().unwrap_or_default();
+        let value: i128 = digits.parse::<i128>().ok()?;
+        let (min, max) = self.range();
+        (min..=max).contains(&value).then_some(value as i64)
+    }
+}
+
+impl fmt::Display for IntegerType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign: char = if self.is_signed() { 'i' } else { 'u' };
+        write!(f, "{sign}{}", self.bits())
     }
 }
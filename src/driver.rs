@@ -0,0 +1,455 @@
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::json;
+
+use rot_lang::{
+    deserialize_bytecode, format_code, run_bytecode_with_exit_code, serialize_bytecode, Bytecode,
+    Compiler, CompilerError, Program, TestResult, Token,
+};
+
+pub const BYTECODE_EXTENSION: &str = "rotc";
+/// Version of the JSON output schema, incremented on incompatible changes
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Format of the compiler output meant for other programs
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown format '{string}', expected text or json")),
+        }
+    }
+}
+
+/// JSON document with the schema version and the value under the given field
+pub fn json_document<T: Serialize>(field: &str, value: &T) -> String {
+    let document = json!({
+        "schema_version": JSON_SCHEMA_VERSION,
+        field: value,
+    });
+    format!("{document:#}\n")
+}
+
+/// Intermediate or final result of the compilation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EmitStage {
+    Tokens,
+    Ast,
+    Ir,
+    Asm,
+    Obj,
+    Exe,
+}
+
+/// Compilation stage to output and the file where it is written.
+/// Textual stages are written to stdout by default and `-` means stdout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emit {
+    pub stage: EmitStage,
+    pub file: Option<String>,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    /// Parse `<stage>` or `<stage>=<file>`
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (stage, file) = match string.split_once('=') {
+            Some((stage, file)) => (stage, Some(file.to_string())),
+            None => (string, None),
+        };
+        let stage: EmitStage = match stage {
+            "tokens" => EmitStage::Tokens,
+            "ast" => EmitStage::Ast,
+            "ir" => EmitStage::Ir,
+            "asm" => EmitStage::Asm,
+            "obj" => EmitStage::Obj,
+            "exe" => EmitStage::Exe,
+            _ => {
+                return Err(format!(
+                    "Unknown stage '{stage}', expected one of tokens, ast, ir, asm, obj, exe"
+                ))
+            }
+        };
+        Ok(Emit { stage, file })
+    }
+}
+
+/// Lex, parse and type check a Rot program without generating any code
+/// and return all found errors
+pub fn check_rot_file(rot_file: &str) -> Vec<CompilerError> {
+    match Compiler::new().add_file(rot_file) {
+        Ok(compiler) => compiler.check(),
+        Err(error) => vec![error],
+    }
+}
+
+/// Compile a Rot program to a native executable and write the requested
+/// compilation stages. The executable is built when no stages are given.
+pub fn compile_rot_file(
+    rot_file: &str,
+    out_file: Option<String>,
+    emits: &[Emit],
+    format: OutputFormat,
+    save_asm: bool,
+    verbose: bool,
+    debug: bool,
+) -> Result<(), CompilerError> {
+    let default_emit = [Emit {
+        stage: EmitStage::Exe,
+        file: None,
+    }];
+    let emits: &[Emit] = if emits.is_empty() {
+        &default_emit
+    } else {
+        emits
+    };
+    let emitted = |stage: EmitStage| emits.iter().filter(move |emit| emit.stage == stage);
+    let last_stage: EmitStage = emits.iter().map(|emit| emit.stage).max().unwrap();
+    // Generated files are named after the output file or the code file
+    let out_file: String = out_file.unwrap_or_else(|| {
+        Path::new(rot_file)
+            .with_extension("")
+            .to_string_lossy()
+            .to_string()
+    });
+    let mut compiler = Compiler::new();
+    compiler.add_file(rot_file)?.debug(debug);
+
+    if emitted(EmitStage::Tokens).next().is_some() {
+        let tokens: Vec<Token> = compiler.tokens()?;
+        for emit in emitted(EmitStage::Tokens) {
+            let contents: String = match format {
+                OutputFormat::Json => json_document("tokens", &tokens),
+                OutputFormat::Text => tokens.iter().map(|token| format!("{token}\n")).collect(),
+            };
+            write_emit(emit, contents.as_bytes())?;
+        }
+    }
+    if last_stage == EmitStage::Tokens {
+        return Ok(());
+    }
+
+    if emitted(EmitStage::Ast).next().is_some() {
+        let program: Program = compiler.parse()?;
+        for emit in emitted(EmitStage::Ast) {
+            let contents: String = match format {
+                OutputFormat::Json => json_document("functions", &program.functions),
+                OutputFormat::Text => program
+                    .functions
+                    .iter()
+                    .map(|function| function.to_string())
+                    .collect(),
+            };
+            write_emit(emit, contents.as_bytes())?;
+        }
+    }
+    if last_stage == EmitStage::Ast {
+        return Ok(());
+    }
+
+    if emitted(EmitStage::Ir).next().is_some() {
+        let ir: String = compiler.ir()?;
+        for emit in emitted(EmitStage::Ir) {
+            write_emit(emit, ir.as_bytes())?;
+        }
+    }
+    if last_stage == EmitStage::Ir {
+        return Ok(());
+    }
+
+    let assembly: String = compiler.assembly()?;
+    for emit in emitted(EmitStage::Asm) {
+        write_emit(emit, assembly.as_bytes())?;
+    }
+    let asm_file: String = format!("{out_file}.s");
+    if save_asm {
+        write_file(&asm_file, assembly.as_bytes())?;
+    }
+    if last_stage == EmitStage::Asm {
+        return Ok(());
+    }
+
+    let obj_file: String = format!("{out_file}.o");
+    write_file(&asm_file, assembly.as_bytes())?;
+    let assembled = run_command("as", &["--64", "-o", &obj_file, &asm_file], verbose);
+    if !save_asm {
+        remove_file(&asm_file);
+    }
+    assembled?;
+    for emit in emitted(EmitStage::Obj) {
+        if let Some(file) = emit.file.as_deref().filter(|file| *file != obj_file) {
+            let object: Vec<u8> = std::fs::read(&obj_file).map_err(CompilerError::IOError)?;
+            write_file_or_stdout(file, &object)?;
+        }
+    }
+
+    let mut linked: Result<(), CompilerError> = Ok(());
+    for emit in emitted(EmitStage::Exe) {
+        let exe_file: &str = emit.file.as_deref().unwrap_or(&out_file);
+        linked = linked.and(run_command("ld", &["-o", exe_file, &obj_file], verbose));
+    }
+    // Object file is kept only if it was requested with its default name
+    if !emitted(EmitStage::Obj).any(|emit| emit.file.is_none()) {
+        remove_file(&obj_file);
+    }
+    linked
+}
+
+fn write_emit(emit: &Emit, contents: &[u8]) -> Result<(), CompilerError> {
+    write_file_or_stdout(emit.file.as_deref().unwrap_or("-"), contents)
+}
+
+fn write_file_or_stdout(file: &str, contents: &[u8]) -> Result<(), CompilerError> {
+    if file == "-" {
+        return std::io::stdout()
+            .write_all(contents)
+            .map_err(CompilerError::IOError);
+    }
+    write_file(file, contents)
+}
+
+fn write_file(file: &str, contents: &[u8]) -> Result<(), CompilerError> {
+    std::fs::write(file, contents).map_err(CompilerError::IOError)
+}
+
+/// Remove an intermediate file. Failing to do so does not fail the compilation.
+fn remove_file(file: &str) {
+    let _ = std::fs::remove_file(file);
+}
+
+fn run_command(program: &str, args: &[&str], verbose: bool) -> Result<(), CompilerError> {
+    if verbose {
+        eprintln!("[CMD] {program} {}", args.join(" "));
+    }
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(CompilerError::IOError)?;
+    if !output.status.success() {
+        return Err(CompilerError::CodegenError(
+            None,
+            format!(
+                "'{program}' failed with {}\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Compile a Rot program to bytecode and save it to `out_file`
+/// which defaults to the code file with the `.rotc` extension
+pub fn compile_rot_file_to_bytecode(
+    rot_file: &str,
+    out_file: Option<String>,
+) -> Result<(), CompilerError> {
+    let bytecode: Bytecode = Compiler::new().add_file(rot_file)?.compile()?;
+    let out_file: String = out_file.unwrap_or_else(|| {
+        Path::new(rot_file)
+            .with_extension(BYTECODE_EXTENSION)
+            .to_string_lossy()
+            .to_string()
+    });
+    std::fs::write(out_file, serialize_bytecode(&bytecode)).map_err(CompilerError::IOError)
+}
+
+/// Rewrite a Rot file in the canonical format. With `check` the file is
+/// left untouched and an error is returned if it is not formatted.
+pub fn format_rot_file(rot_file: &str, check: bool) -> Result<(), CompilerError> {
+    let code: String = std::fs::read_to_string(rot_file).map_err(CompilerError::IOError)?;
+    let formatted: String = format_code(&code, Some(rot_file.to_string()))?;
+    if formatted == code {
+        return Ok(());
+    }
+    if check {
+        return Err(CompilerError::FormatError(
+            None,
+            format!("{rot_file}: File is not formatted"),
+        ));
+    }
+    write_file(rot_file, formatted.as_bytes())
+}
+
+/// Run the test functions of a Rot file and fail if any of them fails
+pub fn test_rot_file(rot_file: &str) -> Result<(), CompilerError> {
+    let results: Vec<TestResult> = Compiler::new()
+        .add_file(rot_file)?
+        .test(&mut std::io::stdout())?;
+    let failed: usize = results.iter().filter(|result| !result.passed()).count();
+    if failed > 0 {
+        return Err(CompilerError::RuntimeError(
+            None,
+            format!("{failed} of {} tests failed", results.len()),
+        ));
+    }
+    Ok(())
+}
+
+/// Run a Rot program as a native executable, or with the virtual machine
+/// which can also run compiled `.rotc` bytecode files.
+/// The arguments are passed to `main` after the name of the Rot file.
+/// Native executables check the indices of arrays only in `debug` mode.
+/// Returns the exit code of the program.
+pub fn run_rot_file(
+    rot_file: &str,
+    args: &[String],
+    use_vm: bool,
+    debug: bool,
+) -> Result<i32, CompilerError> {
+    let is_bytecode_file: bool = Path::new(rot_file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        == Some(BYTECODE_EXTENSION);
+    if !use_vm {
+        if is_bytecode_file {
+            return Err(CompilerError::BytecodeError(
+                None,
+                "Bytecode files can only be run with '--vm'".to_string(),
+            ));
+        }
+        return run_native(rot_file, args, debug);
+    }
+    let bytecode: Bytecode = if is_bytecode_file {
+        let bytes: Vec<u8> = std::fs::read(rot_file).map_err(CompilerError::IOError)?;
+        deserialize_bytecode(&bytes)?
+    } else {
+        Compiler::new().add_file(rot_file)?.compile()?
+    };
+    let args: Vec<String> = std::iter::once(rot_file.to_string())
+        .chain(args.iter().cloned())
+        .collect();
+    let (_, exit_code) = run_bytecode_with_exit_code(&bytecode, &args, &mut std::io::stdout())?;
+    Ok(exit_code)
+}
+
+fn run_native(rot_file: &str, args: &[String], debug: bool) -> Result<i32, CompilerError> {
+    let exe_file: String = std::env::temp_dir()
+        .join(format!("rot-{}", std::process::id()))
+        .to_string_lossy()
+        .to_string();
+    compile_rot_file(
+        rot_file,
+        Some(exe_file.clone()),
+        &[],
+        OutputFormat::Text,
+        false,
+        false,
+        debug,
+    )?;
+    // The program sees the Rot file as its name like with the virtual machine
+    let status: Result<ExitStatus, std::io::Error> =
+        Command::new(&exe_file).arg0(rot_file).args(args).status();
+    remove_file(&exe_file);
+    let status: ExitStatus = status.map_err(CompilerError::IOError)?;
+    // Programs killed by a signal have no exit code
+    status
+        .code()
+        .ok_or_else(|| CompilerError::RuntimeError(None, format!("Program exited with {status}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FOLDER: &str = "tests";
+
+    #[test]
+    fn compile_native_executable() {
+        let exe_file: String = std::env::temp_dir()
+            .join(format!("rot-test-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let rot_file: String = format!("{TEST_FOLDER}/vm_control_flow.rot");
+        compile_rot_file(
+            &rot_file,
+            Some(exe_file.clone()),
+            &[],
+            OutputFormat::Text,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        let output = Command::new(&exe_file).output().unwrap();
+        remove_file(&exe_file);
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "0\n1\n2\nsmall\nmedium\nlarge\ndone\n"
+        );
+    }
+
+    #[test]
+    fn compile_bounds_checks() {
+        let exe_file: String = std::env::temp_dir()
+            .join(format!("rot-test-bounds-{}", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let rot_file: String = format!("{TEST_FOLDER}/array_bounds.rot");
+        compile_rot_file(
+            &rot_file,
+            Some(exe_file.clone()),
+            &[],
+            OutputFormat::Text,
+            false,
+            false,
+            true,
+        )
+        .unwrap();
+        let output = Command::new(&exe_file).output().unwrap();
+        remove_file(&exe_file);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(output.stdout.is_empty());
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            format!("{rot_file}:4:17: Index out of bounds\n")
+        );
+    }
+
+    #[test]
+    fn json_diagnostics() {
+        let rot_file: String = format!("{TEST_FOLDER}/type_check_errors.rot");
+        let document: serde_json::Value =
+            serde_json::from_str(&json_document("diagnostics", &check_rot_file(&rot_file)))
+                .unwrap();
+        assert_eq!(document["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(
+            document["diagnostics"][0],
+            json!({
+                "kind": "type",
+                "location": { "row": 2, "column": 12, "file": rot_file },
+                "message": "Invalid argument types for '+': int bool",
+            })
+        );
+    }
+
+    #[test]
+    fn parse_emit_stages() {
+        assert_eq!(
+            "asm=-".parse::<Emit>().unwrap(),
+            Emit {
+                stage: EmitStage::Asm,
+                file: Some("-".to_string())
+            }
+        );
+        assert_eq!("ir".parse::<Emit>().unwrap().file, None);
+        assert!("bytecode".parse::<Emit>().is_err());
+    }
+}
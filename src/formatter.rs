@@ -0,0 +1,269 @@
+use crate::class::token::{BinaryOperator, Delimiter, Keyword, Token, TokenType};
+use crate::compiler::CompilerError;
+use crate::lexer::tokenize_code_lossless;
+
+const INDENT: &str = "    ";
+
+/// Format Rot code to the canonical style. Line breaks and comments are kept,
+/// spacing between the tokens is normalized and blocks are indented.
+pub fn format_code(code: &str, code_file: Option<String>) -> Result<String, CompilerError> {
+    let tokens: Vec<Token> = tokenize_code_lossless(code, code_file)?;
+    let mut formatter = Formatter::default();
+    for token in &tokens {
+        formatter.add_token(token);
+    }
+    formatter.end_line();
+    Ok(formatter.output)
+}
+
+#[derive(Debug, Default)]
+struct Formatter<'a> {
+    output: String,
+    line: Vec<&'a Token>,
+    /// Number of open blocks
+    depth: usize,
+    /// Number of open parentheses and brackets. Lines are joined inside them
+    /// unless they end with a line comment.
+    paren_depth: usize,
+    blank_line: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn add_token(&mut self, token: &'a Token) {
+        match token.typ {
+            TokenType::Whitespace => {
+                let newlines: usize = token.value.matches('\n').count();
+                let after_comment: bool = self
+                    .line
+                    .last()
+                    .is_some_and(|last| last.typ == TokenType::Comment);
+                if newlines == 0 || (self.paren_depth > 0 && !after_comment) {
+                    return;
+                }
+                self.end_line();
+                // Consecutive empty lines are collapsed into one
+                if newlines > 1 && !self.output.is_empty() {
+                    self.blank_line = true;
+                }
+            }
+            TokenType::Delimiter(Delimiter::OpenParen | Delimiter::OpenSquare) => {
+                self.paren_depth += 1;
+                self.line.push(token);
+            }
+            TokenType::Delimiter(Delimiter::CloseParen | Delimiter::CloseSquare) => {
+                self.paren_depth = self.paren_depth.saturating_sub(1);
+                self.line.push(token);
+            }
+            _ => self.line.push(token),
+        }
+    }
+
+    fn end_line(&mut self) {
+        let Some(first) = self.line.first() else {
+            return;
+        };
+        if self.blank_line && !is_block_end(first) {
+            self.output.push('\n');
+        }
+        self.blank_line = false;
+
+        // Lines starting with a block end are indented like the start of the block
+        let indent: usize = match is_block_end(first) {
+            true => self.depth.saturating_sub(1),
+            false => self.depth,
+        };
+        self.output.push_str(&INDENT.repeat(indent));
+        let mut previous: Option<&Token> = None;
+        // Number of open angle brackets of pointer types, e.g. `ptr<ptr<char>>`
+        let mut type_depth: usize = 0;
+        for token in std::mem::take(&mut self.line) {
+            if let Some(previous) = previous {
+                if needs_space(previous, token) && !is_type_bracket(previous, token, type_depth) {
+                    self.output.push(' ');
+                }
+            }
+            type_depth = match token.typ {
+                TokenType::BinaryOperator(BinaryOperator::LessThan)
+                    if previous.is_some_and(|previous| previous.value == "ptr") =>
+                {
+                    type_depth + 1
+                }
+                TokenType::BinaryOperator(BinaryOperator::GreaterThan) => {
+                    type_depth.saturating_sub(1)
+                }
+                // Nested pointer types can end with a shift, e.g. `ptr<ptr<int>>`
+                TokenType::BinaryOperator(BinaryOperator::ShiftRight) => {
+                    type_depth.saturating_sub(2)
+                }
+                _ => type_depth,
+            };
+            self.output.push_str(&token.value);
+            self.depth = match token.typ {
+                TokenType::Delimiter(Delimiter::OpenCurly)
+                | TokenType::Keyword(Keyword::Do | Keyword::In) => self.depth + 1,
+                TokenType::Delimiter(Delimiter::CloseCurly)
+                | TokenType::Keyword(
+                    Keyword::Done | Keyword::Elif | Keyword::End | Keyword::Endif,
+                ) => self.depth.saturating_sub(1),
+                // `else` ends the previous branch and starts a new one
+                _ => self.depth,
+            };
+            previous = Some(token);
+        }
+        self.output.push('\n');
+    }
+}
+
+/// Tokens which end a block or a branch of a conditional
+fn is_block_end(token: &Token) -> bool {
+    matches!(
+        token.typ,
+        TokenType::Delimiter(Delimiter::CloseCurly)
+            | TokenType::Keyword(
+                Keyword::Done | Keyword::Elif | Keyword::Else | Keyword::End | Keyword::Endif
+            )
+    )
+}
+
+fn needs_space(previous: &Token, next: &Token) -> bool {
+    match (&previous.typ, &next.typ) {
+        (_, TokenType::Comment) => true,
+        (TokenType::Delimiter(Delimiter::OpenParen | Delimiter::OpenSquare), _) => false,
+        // Field name follows the point directly, e.g. `.x`. A point or an index right
+        // after a word is kept attached to it, e.g. `p.x` and `values[0]`, but `p .x`
+        // and `values [0]` are left apart.
+        (TokenType::Delimiter(Delimiter::Point), _) => false,
+        (_, TokenType::Delimiter(Delimiter::Point | Delimiter::OpenSquare)) => {
+            !is_adjacent(previous, next)
+        }
+        (
+            _,
+            TokenType::Delimiter(
+                Delimiter::CloseParen
+                | Delimiter::CloseSquare
+                | Delimiter::Colon
+                | Delimiter::Comma
+                | Delimiter::SemiColon,
+            ),
+        ) => false,
+        // Function name is followed directly by its parameters and `cast` by its type
+        (
+            TokenType::Identifier | TokenType::Keyword(Keyword::Cast),
+            TokenType::Delimiter(Delimiter::OpenParen),
+        ) => false,
+        // Empty block
+        (
+            TokenType::Delimiter(Delimiter::OpenCurly),
+            TokenType::Delimiter(Delimiter::CloseCurly),
+        ) => false,
+        _ => true,
+    }
+}
+
+/// Angle brackets of pointer types are kept next to the type, e.g. `ptr<char>`
+fn is_type_bracket(previous: &Token, next: &Token, type_depth: usize) -> bool {
+    match (&previous.typ, &next.typ) {
+        (TokenType::Identifier, TokenType::BinaryOperator(BinaryOperator::LessThan)) => {
+            previous.value == "ptr"
+        }
+        (TokenType::BinaryOperator(BinaryOperator::LessThan), _)
+        | (
+            _,
+            TokenType::BinaryOperator(BinaryOperator::GreaterThan | BinaryOperator::ShiftRight),
+        ) => type_depth > 0,
+        _ => false,
+    }
+}
+
+/// Token starts right where the previous one ends on the same line
+fn is_adjacent(previous: &Token, next: &Token) -> bool {
+    previous.location.row == next.location.row
+        && previous.location.column + previous.value.len() == next.location.column
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::TEST_FOLDER;
+
+    #[test]
+    fn format_rot_code() {
+        let code: String =
+            std::fs::read_to_string(format!("{TEST_FOLDER}/format_input.rot")).unwrap();
+        let expected: String =
+            std::fs::read_to_string(format!("{TEST_FOLDER}/format_expected.rot")).unwrap();
+        let formatted: String = format_code(&code, None).unwrap();
+        assert_eq!(formatted, expected);
+        // Formatting is idempotent
+        assert_eq!(format_code(&formatted, None).unwrap(), formatted);
+    }
+
+    #[test]
+    fn format_function_parameters() {
+        let code: String =
+            std::fs::read_to_string(format!("{TEST_FOLDER}/parse_function_parameters.rot"))
+                .unwrap();
+        assert_eq!(
+            format_code(&code, None).unwrap(),
+            "function main(a: bool, b: char, c: CustomType) {}\n"
+        );
+    }
+
+    #[test]
+    fn format_comment_in_parentheses() {
+        let code: &str = "fun add(a: int, b: int) -> int { a b + }\n\
+            fun main() {\n    add(1, // one\n  2) print\n    values[ // first\n0] print\n}\n";
+        let formatted: String = format_code(code, None).unwrap();
+        assert_eq!(
+            formatted,
+            "fun add(a: int, b: int) -> int { a b + }\n\
+            fun main() {\n    add(1, // one\n    2) print\n    values[ // first\n    0] print\n}\n"
+        );
+        assert_eq!(format_code(&formatted, None).unwrap(), formatted);
+        crate::Compiler::new()
+            .add_source("format.rot", &formatted)
+            .parse()
+            .unwrap();
+    }
+
+    #[test]
+    fn format_accesses() {
+        assert_eq!(
+            format_code("fun main() { Color.Red print  p . x  dup .y }", None).unwrap(),
+            "fun main() { Color.Red print p .x dup .y }\n"
+        );
+        assert_eq!(
+            format_code("fun main() { 1 values[ 0 ] =  values [1] }", None).unwrap(),
+            "fun main() { 1 values[0] = values [1] }\n"
+        );
+    }
+
+    #[test]
+    fn format_pointer_types() {
+        assert_eq!(
+            format_code(
+                "fun f(p: ptr < ptr<char> >) -> ptr<int> { 1 2 <  p cast (ptr < int >) }",
+                None
+            )
+            .unwrap(),
+            "fun f(p: ptr<ptr<char>>) -> ptr<int> { 1 2 < p cast(ptr<int>) }\n"
+        );
+        // Closing brackets lexed as a shift are kept together but shifts are spaced
+        assert_eq!(
+            format_code(
+                "fun f(p: ptr<ptr<char>>) { p cast( ptr<ptr<int>> ) drop 1 2 >>  1<<  }",
+                None
+            )
+            .unwrap(),
+            "fun f(p: ptr<ptr<char>>) { p cast(ptr<ptr<int>>) drop 1 2 >> 1 << }\n"
+        );
+    }
+
+    #[test]
+    fn format_bindings() {
+        assert_eq!(
+            format_code("fun main() {\n1 2 let a b in\na b +\nprint\nend\n}", None).unwrap(),
+            "fun main() {\n    1 2 let a b in\n        a b +\n        print\n    end\n}\n"
+        );
+    }
+}
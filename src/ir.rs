@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::bytecode::literal_value;
+use crate::class::expression::{Expression, ExpressionType};
+use crate::class::function::Function;
+use crate::class::intrinsic::{intrinsic_from_string, Intrinsic};
+use crate::class::location::Location;
+use crate::class::program::Program;
+use crate::class::signature::Signature;
+use crate::class::statement::{Statement, StatementType};
+use crate::class::structure::Struct;
+use crate::class::token::{BinaryOperator, UnaryOperator};
+use crate::compiler::CompilerError;
+use crate::data_types::DataType;
+use crate::type_checker::{
+    binary_operand_type, binary_operation_type, check_intrinsic, unary_operation_type,
+};
+use crate::vm::Value;
+
+/// Linear stack operations where control flow is expressed with labels and jumps
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Push(Value),
+    /// Binary operation with the integer type its operands are converted to
+    /// or the type of the topmost operand if they are not integers
+    Binary(BinaryOperator, DataType),
+    /// Unary operation with the type of its operand
+    Unary(UnaryOperator, DataType),
+    /// Intrinsic with the type of the topmost value on the stack,
+    /// or the type of the operands for `divmod`
+    Intrinsic(Intrinsic, DataType),
+    Call(String),
+    /// Allocate the struct and move its fields from the stack to it
+    Construct(Struct),
+    /// Replace the address on the top of the stack with the value stored at the offset
+    Load(DataType, usize),
+    /// Abort if the index on the top of the stack is not less than the length of the array
+    CheckIndex(usize),
+    /// Replace the array and the index on the top of the stack with the element of the type
+    LoadElement(DataType),
+    /// Store the value below the array and the index to the element of the type
+    StoreElement(DataType),
+    /// Reinterpret the value on the top of the stack as the type
+    Cast(DataType),
+    /// Push the value of the local variable in the slot of the stack frame
+    GetLocal(usize, DataType),
+    /// Pop the value on the top of the stack to the local variable in the slot
+    SetLocal(usize),
+    Label(usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    Return,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrInstruction {
+    pub operation: Operation,
+    /// Types on the stack after the operation
+    pub stack: Vec<DataType>,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrFunction {
+    pub name: String,
+    pub signature: Signature,
+    /// Number of local variable slots in the stack frame
+    pub locals: usize,
+    pub instructions: Vec<IrInstruction>,
+}
+
+/// Lower type checked functions to the typed stack IR.
+/// Indices of the arrays are checked at runtime if `bounds_checks` is set.
+pub fn generate_ir(
+    program: &Program,
+    bounds_checks: bool,
+) -> Result<Vec<IrFunction>, CompilerError> {
+    let mut ir_functions: Vec<IrFunction> = Vec::new();
+    for function in &program.functions {
+        let mut builder = IrBuilder {
+            program,
+            bounds_checks,
+            instructions: Vec::new(),
+            stack: Some(
+                function
+                    .signature
+                    .parameters
+                    .iter()
+                    .map(|parameter| parameter.typ.clone())
+                    .collect(),
+            ),
+            label_stacks: HashMap::new(),
+            label_count: 0,
+            loops: Vec::new(),
+            locals: Vec::new(),
+            local_count: 0,
+        };
+        let variables = function
+            .signature
+            .parameters
+            .iter()
+            .map(|parameter| parameter.name.clone());
+        builder.bind_locals(variables.collect(), &function.location)?;
+        for statement in &function.statements {
+            builder.lower_statement(statement)?;
+        }
+        let end_location: Location = match function.tokens.last() {
+            Some(token) => token.location.clone(),
+            None => function.location.clone(),
+        };
+        builder.emit(Operation::Return, &end_location)?;
+        ir_functions.push(IrFunction {
+            name: function.name.clone(),
+            signature: function.signature.clone(),
+            locals: builder.local_count,
+            instructions: builder.instructions,
+        });
+    }
+    Ok(ir_functions)
+}
+
+struct LoopLabels {
+    start: usize,
+    end: usize,
+}
+
+struct IrBuilder<'a> {
+    program: &'a Program,
+    bounds_checks: bool,
+    instructions: Vec<IrInstruction>,
+    /// Types on the stack or `None` if the current position is unreachable
+    stack: Option<Vec<DataType>>,
+    /// Types on the stack when jumping to a label
+    label_stacks: HashMap<usize, Vec<DataType>>,
+    label_count: usize,
+    loops: Vec<LoopLabels>,
+    /// Local variables in scope where the index is the slot of the variable
+    locals: Vec<(String, DataType)>,
+    /// Number of slots needed by the local variables of the function
+    local_count: usize,
+}
+
+impl IrBuilder<'_> {
+    fn new_label(&mut self) -> usize {
+        self.label_count += 1;
+        self.label_count - 1
+    }
+
+    /// Append the operation and track the types on the stack after it.
+    /// Operations in unreachable positions are left out.
+    fn emit(&mut self, operation: Operation, location: &Location) -> Result<(), CompilerError> {
+        if let Operation::Label(label) = operation {
+            if self.stack.is_none() {
+                self.stack = self.label_stacks.get(&label).cloned();
+            }
+        }
+        let stack: &mut Vec<DataType> = match &mut self.stack {
+            Some(stack) => stack,
+            None => return Ok(()),
+        };
+        match &operation {
+            Operation::Push(value) => stack.push(value.data_type()),
+            Operation::Binary(operator, _) => {
+                let operands: Vec<DataType> = stack.split_off(stack.len().saturating_sub(2));
+                // Operands have already been validated by the type checker
+                stack.push(binary_operation_type(operator, &operands[0], &operands[1]).unwrap());
+            }
+            Operation::Unary(operator, _) => {
+                let operand: DataType = stack.pop().unwrap();
+                stack.push(unary_operation_type(operator, &operand).unwrap());
+            }
+            Operation::Intrinsic(intrinsic, _) => {
+                check_intrinsic(*intrinsic, stack, &format!("{intrinsic:?}"), location)?
+            }
+            Operation::Call(name) => {
+                let function: &Function = self.program.function(name).unwrap();
+                stack.truncate(
+                    stack
+                        .len()
+                        .saturating_sub(function.signature.parameters.len()),
+                );
+                stack.extend(function.signature.return_type.iter().cloned());
+            }
+            Operation::Construct(structure) => {
+                stack.truncate(stack.len().saturating_sub(structure.fields.len()));
+                stack.push(DataType::Custom(structure.name.clone()));
+            }
+            Operation::Load(typ, _) => {
+                stack.pop();
+                stack.push(typ.clone());
+            }
+            Operation::CheckIndex(_) => {}
+            Operation::LoadElement(typ) => {
+                stack.truncate(stack.len().saturating_sub(2));
+                stack.push(typ.clone());
+            }
+            Operation::StoreElement(_) => stack.truncate(stack.len().saturating_sub(3)),
+            Operation::Cast(typ) => {
+                stack.pop();
+                stack.push(typ.clone());
+            }
+            Operation::GetLocal(_, typ) => stack.push(typ.clone()),
+            Operation::SetLocal(_) => {
+                stack.pop();
+            }
+            Operation::Label(_) => {}
+            Operation::Jump(label) => {
+                self.label_stacks
+                    .entry(*label)
+                    .or_insert_with(|| stack.clone());
+            }
+            Operation::JumpIfFalse(label) => {
+                stack.pop();
+                self.label_stacks
+                    .entry(*label)
+                    .or_insert_with(|| stack.clone());
+            }
+            Operation::Return => {}
+        }
+        let stack: Vec<DataType> = stack.clone();
+        if matches!(operation, Operation::Jump(_) | Operation::Return) {
+            self.stack = None;
+        }
+        self.instructions.push(IrInstruction {
+            operation,
+            stack,
+            location: location.clone(),
+        });
+        Ok(())
+    }
+
+    fn top_type(&self) -> DataType {
+        match self.stack.as_ref().and_then(|stack| stack.last()) {
+            Some(typ) => typ.clone(),
+            // Unreachable operations are not emitted so the type does not matter
+            None => DataType::Integer,
+        }
+    }
+
+    /// Integer type the two topmost values are converted to by the operator
+    /// or the type of the topmost one if they are not integers
+    fn operand_type(&self, operator: &BinaryOperator) -> DataType {
+        let operands: &[DataType] = match &self.stack {
+            Some(stack) => &stack[stack.len().saturating_sub(2)..],
+            None => &[],
+        };
+        match operands {
+            [a, b] => {
+                binary_operand_type(operator, a, b).map_or_else(|| b.clone(), DataType::of_integer)
+            }
+            _ => self.top_type(),
+        }
+    }
+
+    /// Slot of the innermost local variable called `name`
+    fn local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|(local, _)| local == name)
+    }
+
+    /// Pop the values on the top of the stack to new local variables
+    fn bind_locals(
+        &mut self,
+        names: Vec<String>,
+        location: &Location,
+    ) -> Result<(), CompilerError> {
+        let scope: usize = self.locals.len();
+        let types: Vec<DataType> = match &self.stack {
+            Some(stack) => stack[stack.len() - names.len()..].to_vec(),
+            None => vec![DataType::Integer; names.len()],
+        };
+        self.locals.extend(names.into_iter().zip(types));
+        self.local_count = self.local_count.max(self.locals.len());
+        // The last variable is on the top of the stack
+        for slot in (scope..self.locals.len()).rev() {
+            self.emit(Operation::SetLocal(slot), location)?;
+        }
+        Ok(())
+    }
+
+    fn lower_block(&mut self, block: &Statement) -> Result<(), CompilerError> {
+        for statement in block.statements.iter().flatten() {
+            self.lower_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn lower_statement(&mut self, statement: &Statement) -> Result<(), CompilerError> {
+        let location: &Location = &statement.location;
+        match &statement.typ {
+            StatementType::Expression => {
+                if let Some(expression) = &statement.expression {
+                    self.lower_expression(expression)?;
+                }
+            }
+            StatementType::Compound => self.lower_block(statement)?,
+            StatementType::Binding => {
+                let (body, variables) =
+                    statement.statements.as_ref().unwrap().split_last().unwrap();
+                let scope: usize = self.locals.len();
+                let names = variables
+                    .iter()
+                    .map(|variable| variable.value.clone().unwrap_or_default());
+                self.bind_locals(names.collect(), location)?;
+                self.lower_block(body)?;
+                self.locals.truncate(scope);
+            }
+            StatementType::Conditional => {
+                let branches: &Vec<Statement> = statement.statements.as_ref().unwrap();
+                let else_label: usize = self.new_label();
+                let end_label: usize = self.new_label();
+                self.lower_block(&branches[0])?;
+                self.emit(Operation::JumpIfFalse(else_label), location)?;
+                self.lower_block(&branches[1])?;
+                self.emit(Operation::Jump(end_label), location)?;
+                self.emit(Operation::Label(else_label), location)?;
+                if let Some(else_branch) = branches.get(2) {
+                    self.lower_statement(else_branch)?;
+                }
+                self.emit(Operation::Label(end_label), location)?;
+            }
+            StatementType::Loop => {
+                let branches: &Vec<Statement> = statement.statements.as_ref().unwrap();
+                let labels = LoopLabels {
+                    start: self.new_label(),
+                    end: self.new_label(),
+                };
+                self.emit(Operation::Label(labels.start), location)?;
+                self.lower_block(&branches[0])?;
+                self.emit(Operation::JumpIfFalse(labels.end), location)?;
+                self.loops.push(labels);
+                self.lower_block(&branches[1])?;
+                let labels: LoopLabels = self.loops.pop().unwrap();
+                self.emit(Operation::Jump(labels.start), location)?;
+                self.emit(Operation::Label(labels.end), location)?;
+            }
+            StatementType::Break | StatementType::Continue => {
+                let labels: &LoopLabels = match self.loops.last() {
+                    Some(labels) => labels,
+                    None => {
+                        return Err(CompilerError::CodegenError(
+                            Some(location.clone()),
+                            format!("{:?} outside of a loop", statement.typ),
+                        ))
+                    }
+                };
+                let label: usize = match statement.typ {
+                    StatementType::Break => labels.end,
+                    _ => labels.start,
+                };
+                self.emit(Operation::Jump(label), location)?;
+            }
+            StatementType::Return => self.emit(Operation::Return, location)?,
+            StatementType::NoOperation => {}
+            typ => {
+                return Err(CompilerError::CodegenError(
+                    Some(location.clone()),
+                    format!("Statement {typ:?} is not supported"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn lower_expression(&mut self, expression: &Expression) -> Result<(), CompilerError> {
+        let location: &Location = &expression.location;
+        let word: &str = expression.value.as_deref().unwrap_or_default();
+        let operation: Operation = match &expression.typ {
+            ExpressionType::Literal(data_type) => {
+                Operation::Push(literal_value(data_type, word, location)?)
+            }
+            ExpressionType::Binary(BinaryOperator::Assignment) => match expression
+                .expressions
+                .as_deref()
+            {
+                Some([variable]) if variable.typ == ExpressionType::Identifier => {
+                    let name: &str = variable.value.as_deref().unwrap_or_default();
+                    Operation::SetLocal(self.local(name).unwrap())
+                }
+                Some([index]) => Operation::StoreElement(self.lower_index(index)?),
+                _ => unreachable!("Assignment without an element is rejected by the type checker"),
+            },
+            ExpressionType::Binary(operator) => {
+                Operation::Binary(operator.clone(), self.operand_type(operator))
+            }
+            ExpressionType::Unary(operator) => Operation::Unary(operator.clone(), self.top_type()),
+            ExpressionType::Identifier | ExpressionType::FunctionCall => {
+                // Arguments of calls are pushed in order before calling, e.g. `add(34, 35)` is `34 35 add`
+                for argument in expression.expressions.iter().flatten() {
+                    for expression in argument.expressions.iter().flatten() {
+                        self.lower_expression(expression)?;
+                    }
+                }
+                if let Some(slot) = self.local(word) {
+                    Operation::GetLocal(slot, self.locals[slot].1.clone())
+                } else if let Some(Intrinsic::DivMod) = intrinsic_from_string(word) {
+                    Operation::Intrinsic(
+                        Intrinsic::DivMod,
+                        self.operand_type(&BinaryOperator::Division),
+                    )
+                } else if let Some(intrinsic) = intrinsic_from_string(word) {
+                    Operation::Intrinsic(intrinsic, self.top_type())
+                } else if let Some(structure) = self.program.structure(word) {
+                    Operation::Construct(structure.clone())
+                } else if let Some((address, memory)) = self.program.memory(word) {
+                    Operation::Push(Value::Array(memory.typ.clone(), address))
+                } else {
+                    Operation::Call(word.to_string())
+                }
+            }
+            ExpressionType::FieldAccess => {
+                for expression in expression.expressions.iter().flatten() {
+                    self.lower_expression(expression)?;
+                }
+                // Field access has already been validated by the type checker
+                let structure: &Struct = match self.top_type() {
+                    DataType::Custom(name) => self.program.structure(&name).unwrap(),
+                    typ => unreachable!("Field '{word}' of {typ}"),
+                };
+                let (index, field) = structure.field(word).unwrap();
+                Operation::Load(field.typ.clone(), structure.layout().offsets[index])
+            }
+            ExpressionType::Index => Operation::LoadElement(self.lower_index(expression)?),
+            ExpressionType::Cast(typ) => Operation::Cast(typ.clone()),
+            typ => {
+                return Err(CompilerError::CodegenError(
+                    Some(location.clone()),
+                    format!("Expression {typ:?} is not supported"),
+                ))
+            }
+        };
+        self.emit(operation, location)
+    }
+
+    /// Lower the array and the index of the `Index` expression and return the type of the element
+    fn lower_index(&mut self, index: &Expression) -> Result<DataType, CompilerError> {
+        for expression in index.expressions.iter().flatten() {
+            match expression.typ {
+                ExpressionType::Enclosure => {
+                    for expression in expression.expressions.iter().flatten() {
+                        self.lower_expression(expression)?;
+                    }
+                }
+                _ => self.lower_expression(expression)?,
+            }
+        }
+        // Array is below the index on the stack
+        let array: Option<DataType> = self
+            .stack
+            .as_ref()
+            .and_then(|stack| stack.iter().rev().nth(1).cloned());
+        let Some(DataType::Array(element, length)) = array else {
+            // Unreachable operations are not emitted so the type does not matter
+            return Ok(DataType::Integer);
+        };
+        if self.bounds_checks {
+            self.emit(Operation::CheckIndex(length), &index.location)?;
+        }
+        Ok(*element)
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Push(Value::Character(character)) => write!(f, "push char {character:?}"),
+            Operation::Push(Value::String(string)) => write!(f, "push str {string:?}"),
+            Operation::Push(value) => write!(f, "push {} {value}", value.data_type()),
+            Operation::Binary(operator, typ) => write!(f, "{operator:?} {typ}"),
+            Operation::Unary(operator, typ) => write!(f, "{operator:?} {typ}"),
+            Operation::Intrinsic(intrinsic, typ) => write!(f, "{intrinsic:?} {typ}"),
+            Operation::Call(name) => write!(f, "call {name}"),
+            Operation::Construct(structure) => write!(f, "construct {}", structure.name),
+            Operation::Load(typ, offset) => write!(f, "load {typ} +{offset}"),
+            Operation::CheckIndex(length) => write!(f, "check_index {length}"),
+            Operation::LoadElement(typ) => write!(f, "load_element {typ}"),
+            Operation::StoreElement(typ) => write!(f, "store_element {typ}"),
+            Operation::Cast(typ) => write!(f, "cast {typ}"),
+            Operation::GetLocal(slot, typ) => write!(f, "get_local {slot} {typ}"),
+            Operation::SetLocal(slot) => write!(f, "set_local {slot}"),
+            Operation::Label(label) => write!(f, "label {label}"),
+            Operation::Jump(label) => write!(f, "jump {label}"),
+            Operation::JumpIfFalse(label) => write!(f, "jump_if_false {label}"),
+            Operation::Return => write!(f, "return"),
+        }
+    }
+}
+
+/// Instruction is displayed as the operation followed by the types on the stack
+impl fmt::Display for IrInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stack: Vec<String> = self.stack.iter().map(|typ| typ.to_string()).collect();
+        write!(f, "{} [{}]", self.operation, stack.join(" "))
+    }
+}
+
+impl fmt::Display for IrFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:", self.name)?;
+        for instruction in &self.instructions {
+            writeln!(f, "  {instruction}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class::structure::Layout;
+    use crate::{constant::TEST_FOLDER, Compiler};
+
+    #[test]
+    fn ir_stack_types() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_file(&format!("{TEST_FOLDER}/vm_control_flow.rot"))
+            .unwrap();
+        let ir: Vec<IrFunction> = generate_ir(&compiler.parse().unwrap(), false).unwrap();
+        let classify: &IrFunction = ir
+            .iter()
+            .find(|function| function.name == "classify")
+            .unwrap();
+        let operations: Vec<String> = classify
+            .instructions
+            .iter()
+            .take(6)
+            .map(|instruction| instruction.to_string())
+            .collect();
+        // The parameter is moved from the stack to a local first
+        assert_eq!(
+            operations,
+            vec![
+                "set_local 0 []",
+                "get_local 0 int [int]",
+                "push int 10 [int int]",
+                "LessThan int [bool]",
+                "jump_if_false 0 []",
+                "push str \"small\" [str]",
+            ]
+        );
+        // Every function ends with a return with only the return values left on the stack
+        for function in &ir {
+            let last: &IrInstruction = function.instructions.last().unwrap();
+            assert_eq!(last.operation, Operation::Return);
+            assert!(last.stack.is_empty());
+        }
+    }
+
+    #[test]
+    fn ir_struct_layout() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_file(&format!("{TEST_FOLDER}/structs.rot"))
+            .unwrap();
+        let program: Program = compiler.parse().unwrap();
+        let label: Layout = program.structure("Label").unwrap().layout();
+        // Fields are aligned to their size so the bool is padded before the pointer
+        assert_eq!(
+            label,
+            Layout {
+                size: 24,
+                alignment: 8,
+                offsets: vec![0, 8, 16]
+            }
+        );
+
+        let ir: Vec<IrFunction> = generate_ir(&program, false).unwrap();
+        let main: &IrFunction = ir.iter().find(|function| function.name == "main").unwrap();
+        let operations: Vec<String> = main
+            .instructions
+            .iter()
+            .skip(13)
+            .take(2)
+            .map(|instruction| instruction.to_string())
+            .collect();
+        assert_eq!(
+            operations,
+            vec!["construct Label [Label]", "Dup Label [Label Label]"]
+        );
+    }
+}
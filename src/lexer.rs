@@ -1,36 +1,64 @@
+use lazy_static::lazy_static;
 use regex::{Captures, Match, Regex};
 
 use crate::class::location::Location;
 use crate::class::token::{Token, TokenType, TOKEN_REGEXES};
 use crate::compiler::CompilerError;
 
+lazy_static! {
+    /// Token regexes are compiled once instead of for every token
+    static ref COMPILED_TOKEN_REGEXES: Vec<(Regex, &'static TokenType)> = TOKEN_REGEXES
+        .entries()
+        .map(|(regex, token_type)| (Regex::new(regex).unwrap(), token_type))
+        .collect();
+}
+
+#[cfg(test)]
 pub fn tokenize_code_file(file: &str) -> Result<Vec<Token>, CompilerError> {
     let code: String = match std::fs::read_to_string(file) {
         Ok(string) => string,
         Err(error) => return Err(CompilerError::IOError(error)),
     };
-    Ok(tokenize_code(&code, Some(file.to_string())))
+    tokenize_code(&code, Some(file.to_string()))
+}
+
+pub fn tokenize_code(code: &str, code_file: Option<String>) -> Result<Vec<Token>, CompilerError> {
+    tokenize(code, code_file, false)
+}
+
+/// Tokenize the code keeping the whitespace and comments so that
+/// the original code can be reconstructed from the tokens
+pub fn tokenize_code_lossless(
+    code: &str,
+    code_file: Option<String>,
+) -> Result<Vec<Token>, CompilerError> {
+    tokenize(code, code_file, true)
 }
 
-pub fn tokenize_code(code: &str, code_file: Option<String>) -> Vec<Token> {
+fn tokenize(
+    code: &str,
+    code_file: Option<String>,
+    keep_trivia: bool,
+) -> Result<Vec<Token>, CompilerError> {
     let mut tokens: Vec<Token> = Vec::new();
     let mut row: usize = 1;
     let mut column: usize = 1;
     let mut cursor: usize = 0;
     loop {
         let token: Option<Token> = get_next_token(
-            &code,
+            code,
             code_file.clone(),
             &mut cursor,
             &mut row,
-            &mut column
-        );
+            &mut column,
+            keep_trivia,
+        )?;
         if token.is_none() {
             break;
         }
         tokens.push(token.unwrap());
     }
-    return tokens;
+    Ok(tokens)
 }
 
 fn get_next_token(
@@ -39,19 +67,20 @@ fn get_next_token(
     cursor: &mut usize,
     row: &mut usize,
     column: &mut usize,
-) -> Option<Token> {
+    keep_trivia: bool,
+) -> Result<Option<Token>, CompilerError> {
     if *cursor >= code.len() {
-        return None;
+        return Ok(None);
     }
 
     // Test if the remaining code matches with any Token regex
     let unparsed_code: &str = code.split_at(*cursor).1;
-    for (regex, token_type) in TOKEN_REGEXES.entries() {
-        let captures: Option<Captures> = Regex::new(regex).unwrap().captures(unparsed_code);
-        if !captures.is_none() {
+    for (regex, token_type) in COMPILED_TOKEN_REGEXES.iter() {
+        let captures: Option<Captures> = regex.captures(unparsed_code);
+        if let Some(captures) = captures {
             // Take match from capture group if it is explicitly specified
-            let whole_match: Option<Match> = captures.as_ref().unwrap().get(0);
-            let mut token_match: Option<Match> = captures.unwrap().get(1);
+            let whole_match: Option<Match> = captures.get(0);
+            let mut token_match: Option<Match> = captures.get(1);
             if token_match.is_none() {
                 token_match = whole_match;
             }
@@ -74,33 +103,23 @@ fn get_next_token(
             *cursor += whole_match.unwrap().end();
 
             // Token should be skipped, e.g. whitespace or comment
-            if token_type == &TokenType::None {
-                return get_next_token(code, code_file, cursor, row, column);
+            let is_trivia: bool = matches!(token_type, TokenType::Whitespace | TokenType::Comment);
+            if is_trivia && !keep_trivia {
+                return get_next_token(code, code_file, cursor, row, column, keep_trivia);
             }
-            return Some(Token {
+            return Ok(Some(Token {
                 value: match_str.to_string(),
-                typ: get_token_type(match_str),
+                typ: (*token_type).clone(),
                 location: Location::new(token_row, token_column, code_file),
-            });
+            }));
         }
     }
 
-    // TODO: Enhance error reporting
-    panic!(
-        "Unknown Token at the start of the following code:\n{}",
-        unparsed_code
-    )
-}
-
-pub fn get_token_type(token: &str) -> TokenType {
-    for (regex, token_type) in TOKEN_REGEXES.entries() {
-        // Take match from capture group if it is explicitly specified
-        let is_match: bool = Regex::new(regex).unwrap().is_match(token);
-        if is_match {
-            return token_type.clone();
-        }
-    }
-    panic!("Did not get TokenType for '{}'", token);
+    let unknown_token: &str = unparsed_code.split_whitespace().next().unwrap_or_default();
+    Err(CompilerError::LexerError(
+        Some(Location::new(*row, *column, code_file)),
+        format!("Unknown token '{}'", unknown_token),
+    ))
 }
 
 #[cfg(test)]
@@ -109,9 +128,9 @@ mod tests {
 
     use super::*;
     use crate::{
-        class::token::{Delimiter, Keyword, BinaryOperator},
+        class::token::{BinaryOperator, Delimiter, Keyword, UnaryOperator},
         constant::TEST_FOLDER,
-        data_types::DataType,
+        data_types::{DataType, IntegerType},
     };
 
     #[test]
@@ -134,6 +153,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn lex_unknown_token() {
+        match tokenize_code_file(&format!("{TEST_FOLDER}/lex_unknown_token.rot")) {
+            Err(CompilerError::LexerError(Some(location), message)) => {
+                let file: String = format!("{TEST_FOLDER}/lex_unknown_token.rot");
+                assert_eq!(location, Location::new(1, 4, Some(file)));
+                assert_eq!(message, "Unknown token '@'");
+            }
+            _ => panic!("Expected lexer error"),
+        }
+    }
+
     #[test]
     fn lex_empty_file() {
         let tokens: Vec<Token> =
@@ -155,6 +186,20 @@ mod tests {
         assert!(tokens.is_empty())
     }
 
+    #[test]
+    fn lex_lossless() {
+        let file: String = format!("{TEST_FOLDER}/lex_comments.rot");
+        let code: String = std::fs::read_to_string(&file).unwrap();
+        let tokens: Vec<Token> = tokenize_code_lossless(&code, Some(file)).unwrap();
+        let comments: usize = tokens
+            .iter()
+            .filter(|token| token.typ == TokenType::Comment)
+            .count();
+        assert_eq!(comments, 2);
+        let lexed_code: String = tokens.iter().map(|token| token.value.as_str()).collect();
+        assert_eq!(lexed_code, code);
+    }
+
     #[test]
     fn lex_binary_operators() {
         let tokens: Vec<Token> =
@@ -167,6 +212,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn lex_unary_operators() {
+        let tokens: Vec<Token> =
+            tokenize_code_file(&format!("{TEST_FOLDER}/lex_unary_operators.rot")).unwrap();
+        assert_eq!(tokens.len(), UnaryOperator::COUNT);
+        for (i, operator) in UnaryOperator::iter().enumerate() {
+            assert_eq!(TokenType::UnaryOperator(operator), tokens[i].typ)
+        }
+        // Words starting like the operators are identifiers
+        let tokens: Vec<Token> = tokenize_code("nothing order android", None).unwrap();
+        assert!(tokens
+            .iter()
+            .all(|token| token.typ == TokenType::Identifier));
+    }
+
     #[test]
     fn lex_delimiters() {
         let tokens: Vec<Token> =
@@ -184,7 +244,7 @@ mod tests {
         let tokens: Vec<Token> =
             tokenize_code_file(&format!("{TEST_FOLDER}/lex_literals.rot")).unwrap();
         // Are all DataTypes taken into account in the test file
-        assert_eq!(tokens.len(), DataType::COUNT);
+        assert_eq!(tokens.len(), DataType::iter().len());
         // Are tokens lexed correctly as literal with certain type
         for (i, data_type) in DataType::iter().enumerate() {
             // Do not test custom data types
@@ -195,6 +255,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn lex_integer_literals() {
+        let tokens: Vec<Token> =
+            tokenize_code_file(&format!("{TEST_FOLDER}/lex_integer_literals.rot")).unwrap();
+        // Every integer type has a suffix and literals without one are int
+        assert_eq!(tokens.len(), IntegerType::iter().len() + 1);
+        for (token, typ) in tokens.iter().zip(IntegerType::iter()) {
+            assert_eq!(TokenType::Literal(DataType::of_integer(typ)), token.typ)
+        }
+        assert_eq!(
+            TokenType::Literal(DataType::Integer),
+            tokens.last().unwrap().typ
+        )
+    }
+
     #[test]
     fn lex_keywords() {
         let tokens: Vec<Token> =
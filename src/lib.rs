@@ -0,0 +1,309 @@
+//! Compiler for the Rot programming language.
+//!
+//! The [`Compiler`] collects Rot sources and checks, compiles or runs them:
+//!
+//! ```
+//! let mut output: Vec<u8> = Vec::new();
+//! rot_lang::Compiler::new()
+//!     .add_source("main.rot", "fun main() { 34 35 + print }")
+//!     .run(&mut output)
+//!     .unwrap();
+//! assert_eq!(output, b"69\n");
+//! ```
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+pub use bytecode::{deserialize_bytecode, serialize_bytecode, Bytecode};
+pub use class::location::Location;
+pub use class::program::Program;
+pub use class::token::Token;
+pub use compiler::CompilerError;
+pub use formatter::format_code;
+pub use lsp::run_lsp;
+pub use repl::run_repl;
+pub use test_runner::TestResult;
+pub use vm::{run_bytecode_with_exit_code, Value};
+
+use bytecode::compile_bytecode;
+use compiler::{check_main_defined, merge_programs, parse_program, resolve_constants};
+use constant::MAIN_FUNCTION_NAME;
+use lexer::tokenize_code;
+use standard_library::{standard_library_file, STANDARD_LIBRARY_PREFIX};
+use test_runner::run_tests;
+use type_checker::{find_warnings, type_check_functions};
+use vm::run_bytecode;
+
+pub(crate) mod assembly;
+pub(crate) mod bytecode;
+pub(crate) mod class;
+pub(crate) mod compiler;
+pub(crate) mod constant;
+pub(crate) mod data_types;
+pub(crate) mod formatter;
+pub(crate) mod ir;
+pub(crate) mod lexer;
+pub(crate) mod lsp;
+pub(crate) mod repl;
+pub(crate) mod standard_library;
+pub(crate) mod test_runner;
+pub(crate) mod type_checker;
+pub(crate) mod vm;
+
+/// Rot code and the name of its file used in the error locations
+#[derive(Debug, Clone)]
+struct Source {
+    name: String,
+    code: String,
+}
+
+/// Compiles a Rot program made of one or more sources
+#[derive(Debug, Clone, Default)]
+pub struct Compiler {
+    sources: Vec<Source>,
+    debug: bool,
+    args: Vec<String>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add Rot code to the program. The name is used in the error locations.
+    pub fn add_source(&mut self, name: &str, code: &str) -> &mut Self {
+        self.sources.push(Source {
+            name: name.to_string(),
+            code: code.to_string(),
+        });
+        self
+    }
+
+    /// Add the code from a Rot file to the program
+    pub fn add_file(&mut self, file: &str) -> Result<&mut Self, CompilerError> {
+        let code: String = std::fs::read_to_string(file).map_err(CompilerError::IOError)?;
+        Ok(self.add_source(file, &code))
+    }
+
+    /// Check the indices of arrays at runtime in the generated assembly.
+    /// The virtual machine always checks them.
+    pub fn debug(&mut self, debug: bool) -> &mut Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Command-line arguments passed to `main` by `run`, starting with the program name
+    pub fn args(&mut self, args: &[String]) -> &mut Self {
+        self.args = args.to_vec();
+        self
+    }
+
+    /// Tokens of all sources in the order they were added
+    pub fn tokens(&self) -> Result<Vec<Token>, CompilerError> {
+        let mut tokens: Vec<Token> = Vec::new();
+        for source in &self.sources {
+            tokens.extend(tokenize_code(&source.code, Some(source.name.clone()))?);
+        }
+        Ok(tokens)
+    }
+
+    /// Parse the program and the files it includes without type checking them
+    pub fn parse(&self) -> Result<Program, CompilerError> {
+        let program: Program = self.parse_definitions()?;
+        check_main_defined(&program.functions)?;
+        Ok(program)
+    }
+
+    /// Parse the program without requiring the `main` function.
+    /// Included files are added once and their paths are relative to the including file.
+    /// Paths starting with `std/` refer to the standard library embedded in the compiler.
+    pub fn parse_definitions(&self) -> Result<Program, CompilerError> {
+        let mut program = Program::default();
+        let mut names: HashMap<String, Location> = HashMap::new();
+        let mut sources: Vec<Source> = self.sources.clone();
+        let mut index: usize = 0;
+        while let Some(source) = sources.get(index).cloned() {
+            index += 1;
+            let tokens: Vec<Token> = tokenize_code(&source.code, Some(source.name.clone()))?;
+            let items: Program = parse_program(&tokens)?;
+            for include in &items.includes {
+                let is_standard: bool = include.path.starts_with(STANDARD_LIBRARY_PREFIX);
+                let path: String = match is_standard {
+                    true => include.path.clone(),
+                    false => Path::new(&source.name)
+                        .with_file_name(&include.path)
+                        .to_string_lossy()
+                        .to_string(),
+                };
+                if sources.iter().any(|source| source.name == path) {
+                    continue;
+                }
+                let code: String = match is_standard {
+                    true => standard_library_file(&path)
+                        .map(str::to_string)
+                        .ok_or_else(|| {
+                            CompilerError::ParserError(
+                                Some(include.location.clone()),
+                                format!(
+                                    "Cannot include '{}': No such file in the standard library",
+                                    include.path
+                                ),
+                            )
+                        })?,
+                    false => std::fs::read_to_string(&path).map_err(|error| {
+                        CompilerError::ParserError(
+                            Some(include.location.clone()),
+                            format!("Cannot include '{}': {error}", include.path),
+                        )
+                    })?,
+                };
+                sources.push(Source { name: path, code });
+            }
+            merge_programs(&mut program, &mut names, items)?;
+        }
+        resolve_constants(&mut program);
+        Ok(program)
+    }
+
+    /// Find all errors and warnings in the program without generating any code
+    pub fn check(&self) -> Vec<CompilerError> {
+        match self.parse() {
+            Ok(program) => {
+                let mut diagnostics: Vec<CompilerError> = type_check_functions(&program);
+                diagnostics.extend(find_warnings(&program));
+                diagnostics
+            }
+            Err(error) => vec![error],
+        }
+    }
+
+    /// Type checked program. Returns the first found error.
+    pub fn program(&self) -> Result<Program, CompilerError> {
+        let program: Program = self.parse()?;
+        match type_check_functions(&program).into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(program),
+        }
+    }
+
+    /// Compile the program to bytecode for the virtual machine
+    pub fn compile(&self) -> Result<Bytecode, CompilerError> {
+        compile_bytecode(&self.program()?, MAIN_FUNCTION_NAME)
+    }
+
+    /// Intermediate representation of the program from which the assembly is generated
+    pub fn ir(&self) -> Result<String, CompilerError> {
+        let ir = ir::generate_ir(&self.program()?, self.debug)?;
+        Ok(ir.iter().map(|function| function.to_string()).collect())
+    }
+
+    /// Generate x86-64 assembly for Linux from the program
+    pub fn assembly(&self) -> Result<String, CompilerError> {
+        let ir = ir::generate_ir(&self.program()?, self.debug)?;
+        Ok(assembly::generate_assembly(&ir))
+    }
+
+    /// Run the program with the virtual machine and return the values left on the stack
+    pub fn run(&self, output: &mut dyn Write) -> Result<Vec<Value>, CompilerError> {
+        run_bytecode(&self.compile()?, &self.args, output)
+    }
+
+    /// Run the test functions of the program and write a report of the results.
+    /// The program does not need the `main` function.
+    pub fn test(&self, output: &mut dyn Write) -> Result<Vec<TestResult>, CompilerError> {
+        let program: Program = self.parse_definitions()?;
+        if let Some(error) = type_check_functions(&program).into_iter().next() {
+            return Err(error);
+        }
+        run_tests(&program, output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::TEST_FOLDER;
+
+    #[test]
+    fn compiler_multiple_sources() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_source("square.rot", "fun square(n: int) -> int { n n * }")
+            .add_source("main.rot", "fun main() {\n  7 square print\n}");
+        let mut output: Vec<u8> = Vec::new();
+        assert!(compiler.run(&mut output).unwrap().is_empty());
+        assert_eq!(String::from_utf8(output).unwrap(), "49\n");
+        assert!(compiler.assembly().unwrap().contains("fun_square:"));
+
+        // Errors point to the source where they are found
+        let errors: Vec<CompilerError> = Compiler::new()
+            .add_source("main.rot", "fun main() {\n  1 true +\n}")
+            .check();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].located_message().0.unwrap().to_string(),
+            "main.rot:2:10"
+        );
+    }
+
+    #[test]
+    fn compiler_add_file() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_file(&format!("{TEST_FOLDER}/vm_control_flow.rot"))
+            .unwrap();
+        assert_eq!(compiler.parse().unwrap().functions.len(), 2);
+        assert!(compiler.check().is_empty());
+        assert!(Compiler::new().add_file("missing.rot").is_err());
+    }
+
+    #[test]
+    fn compiler_includes() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_file(&format!("{TEST_FOLDER}/parse_items.rot"))
+            .unwrap();
+        let program: Program = compiler.parse().unwrap();
+        let names: Vec<&str> = program
+            .functions
+            .iter()
+            .map(|function| function.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main", "twice"]);
+        assert_eq!(
+            program.functions[1].location.file,
+            Some(format!("{TEST_FOLDER}/parse_items_include.rot"))
+        );
+
+        let error: CompilerError = Compiler::new()
+            .add_source("main.rot", "include \"missing.rot\"\nfun main() {}")
+            .parse()
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Parser error: main.rot:1:1: Cannot include 'missing.rot'"));
+
+        // Standard library is embedded in the compiler
+        let program: Program = Compiler::new()
+            .add_source(
+                "main.rot",
+                "include \"std/linux.rot\"\nfun main() { SYS_EXIT drop }",
+            )
+            .parse()
+            .unwrap();
+        let exit = program
+            .constants
+            .iter()
+            .find(|constant| constant.name == "SYS_EXIT");
+        assert_eq!(exit.map(|constant| constant.value.as_str()), Some("60"));
+        let error: CompilerError = Compiler::new()
+            .add_source("main.rot", "include \"std/missing.rot\"\nfun main() {}")
+            .parse()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parser error: main.rot:1:1: Cannot include 'std/missing.rot': No such file in the standard library"
+        );
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, Diagnostic,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, MarkupContent, MarkupKind, NumberOrString, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, SymbolKind, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::class::function::Function;
+use crate::class::intrinsic::{intrinsic_from_string, Intrinsic};
+use crate::class::location::Location;
+use crate::class::token::{Keyword, Token, TokenType};
+use crate::compiler::CompilerError;
+use crate::lexer::tokenize_code;
+use crate::type_checker::{find_warnings, type_check_functions};
+use crate::Compiler;
+
+/// Serve the Language Server Protocol over stdin and stdout
+pub fn run_lsp() -> Result<(), CompilerError> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(Default::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    let capabilities = serde_json::to_value(capabilities).map_err(protocol_error)?;
+    connection
+        .initialize(capabilities)
+        .map_err(protocol_error)?;
+
+    let mut server = LanguageServer::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection
+                    .handle_shutdown(&request)
+                    .map_err(protocol_error)?
+                {
+                    break;
+                }
+                let response: Response = server.handle_request(request);
+                connection
+                    .sender
+                    .send(response.into())
+                    .map_err(protocol_error)?;
+            }
+            Message::Notification(notification) => {
+                for notification in server.handle_notification(notification) {
+                    connection
+                        .sender
+                        .send(notification.into())
+                        .map_err(protocol_error)?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    drop(connection);
+    io_threads.join().map_err(CompilerError::IOError)
+}
+
+fn protocol_error(error: impl ToString) -> CompilerError {
+    CompilerError::IOError(std::io::Error::other(error.to_string()))
+}
+
+#[derive(Debug, Default)]
+struct LanguageServer {
+    documents: HashMap<Url, Document>,
+}
+
+impl LanguageServer {
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            GotoDefinition::METHOD => respond(request, |params: GotoDefinitionParams| {
+                let position = params.text_document_position_params;
+                let uri: Url = position.text_document.uri;
+                let range: Range = self.documents.get(&uri)?.definition(position.position)?;
+                Some(GotoDefinitionResponse::Scalar(lsp_types::Location {
+                    uri,
+                    range,
+                }))
+            }),
+            HoverRequest::METHOD => respond(request, |params: HoverParams| {
+                let position = params.text_document_position_params;
+                self.documents
+                    .get(&position.text_document.uri)?
+                    .hover(position.position)
+            }),
+            Completion::METHOD => respond(request, |params: CompletionParams| {
+                let uri: &Url = &params.text_document_position.text_document.uri;
+                let document: &Document = self.documents.get(uri)?;
+                Some(CompletionResponse::Array(document.completions()))
+            }),
+            DocumentSymbolRequest::METHOD => respond(request, |params: DocumentSymbolParams| {
+                let document: &Document = self.documents.get(&params.text_document.uri)?;
+                Some(DocumentSymbolResponse::Nested(document.symbols()))
+            }),
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported method '{method}'"),
+            ),
+        }
+    }
+
+    /// Update the open documents and return the diagnostics to publish
+    fn handle_notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let (uri, text): (Url, Option<String>) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                match serde_json::from_value::<DidOpenTextDocumentParams>(notification.params) {
+                    Ok(params) => (params.text_document.uri, Some(params.text_document.text)),
+                    Err(_) => return Vec::new(),
+                }
+            }
+            DidChangeTextDocument::METHOD => {
+                match serde_json::from_value::<DidChangeTextDocumentParams>(notification.params) {
+                    // The server asks for the full text on every change
+                    Ok(mut params) => match params.content_changes.pop() {
+                        Some(change) => (params.text_document.uri, Some(change.text)),
+                        None => return Vec::new(),
+                    },
+                    Err(_) => return Vec::new(),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                match serde_json::from_value::<DidCloseTextDocumentParams>(notification.params) {
+                    Ok(params) => (params.text_document.uri, None),
+                    Err(_) => return Vec::new(),
+                }
+            }
+            _ => return Vec::new(),
+        };
+
+        let diagnostics: Vec<Diagnostic> = match text {
+            Some(text) => {
+                let document = Document::new(&uri, text);
+                let diagnostics: Vec<Diagnostic> = document.diagnostics();
+                self.documents.insert(uri.clone(), document);
+                diagnostics
+            }
+            // Diagnostics of closed documents are cleared
+            None => {
+                self.documents.remove(&uri);
+                Vec::new()
+            }
+        };
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        vec![Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )]
+    }
+}
+
+/// Respond to the request with the result of the handler
+fn respond<P, R>(request: Request, handler: impl FnOnce(P) -> R) -> Response
+where
+    P: serde::de::DeserializeOwned,
+    R: Serialize,
+{
+    match serde_json::from_value::<P>(request.params) {
+        Ok(params) => Response::new_ok(request.id, handler(params)),
+        Err(error) => Response::new_err(
+            request.id,
+            ErrorCode::InvalidParams as i32,
+            error.to_string(),
+        ),
+    }
+}
+
+/// Function, constant or enum defined in a document
+#[derive(Debug, Clone)]
+struct Definition {
+    kind: SymbolKind,
+    keyword: Token,
+    name: Token,
+}
+
+/// Open text document and the results of analyzing it
+#[derive(Debug)]
+struct Document {
+    /// Lines of the text to convert the byte columns of the tokens to LSP positions
+    lines: Vec<String>,
+    tokens: Vec<Token>,
+    functions: Vec<Function>,
+    definitions: Vec<Definition>,
+    errors: Vec<CompilerError>,
+}
+
+impl Document {
+    fn new(uri: &Url, text: String) -> Self {
+        let file: String = match uri.to_file_path() {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => uri.to_string(),
+        };
+        let mut document = Document {
+            lines: text.split('\n').map(str::to_string).collect(),
+            tokens: Vec::new(),
+            functions: Vec::new(),
+            definitions: Vec::new(),
+            errors: Vec::new(),
+        };
+        document.tokens = match tokenize_code(&text, Some(file.clone())) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                document.errors.push(error);
+                return document;
+            }
+        };
+        document.definitions = find_definitions(&document.tokens);
+        // Documents do not need the main function as they can be included by others
+        match Compiler::new().add_source(&file, &text).parse_definitions() {
+            Ok(program) => {
+                // Errors of the included files are reported in their own documents
+                let in_document = |error: &CompilerError| match error.located_message().0 {
+                    Some(location) => location.file.as_ref() == Some(&file),
+                    None => true,
+                };
+                document.errors = type_check_functions(&program)
+                    .into_iter()
+                    .chain(find_warnings(&program))
+                    .filter(in_document)
+                    .collect();
+                document.functions = program.functions;
+            }
+            Err(error) => document.errors.push(error),
+        }
+        document
+    }
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .map(|error| {
+                let (location, message) = error.located_message();
+                let range: Range = match location {
+                    Some(location) => self.location_range(&location),
+                    None => Range::default(),
+                };
+                Diagnostic {
+                    range,
+                    severity: match error {
+                        CompilerError::Warning(..) => Some(DiagnosticSeverity::WARNING),
+                        _ => Some(DiagnosticSeverity::ERROR),
+                    },
+                    code: Some(NumberOrString::String(error.kind().to_string())),
+                    source: Some("rot".to_string()),
+                    message,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// Range of the token at the location or an empty range if there is none
+    fn location_range(&self, location: &Location) -> Range {
+        match self.tokens.iter().find(|token| {
+            token.location.row == location.row && token.location.column == location.column
+        }) {
+            Some(token) => self.token_range(token),
+            None => {
+                let position: Position = self.position(location.row, location.column);
+                Range::new(position, position)
+            }
+        }
+    }
+
+    fn token_at(&self, position: Position) -> Option<&Token> {
+        self.tokens.iter().find(|token| {
+            let range: Range = self.token_range(token);
+            range.start <= position && position < range.end
+        })
+    }
+
+    fn definition(&self, position: Position) -> Option<Range> {
+        let token: &Token = self.token_at(position)?;
+        let definition: &Definition = self
+            .definitions
+            .iter()
+            .find(|definition| definition.name.value == token.value)?;
+        Some(self.token_range(&definition.name))
+    }
+
+    fn hover(&self, position: Position) -> Option<Hover> {
+        let token: &Token = self.token_at(position)?;
+        if token.typ != TokenType::Identifier {
+            return None;
+        }
+        let stack_effect: String = match intrinsic_from_string(&token.value) {
+            Some(intrinsic) => intrinsic.stack_effect().to_string(),
+            None => self
+                .functions
+                .iter()
+                .find(|function| function.name == token.value)?
+                .signature
+                .to_string(),
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```rot\n{} : {stack_effect}\n```", token.value),
+            }),
+            range: Some(self.token_range(token)),
+        })
+    }
+
+    fn completions(&self) -> Vec<CompletionItem> {
+        let mut completions: Vec<CompletionItem> = Vec::new();
+        for definition in &self.definitions {
+            let signature = self
+                .functions
+                .iter()
+                .find(|function| function.name == definition.name.value)
+                .map(|function| function.signature.to_string());
+            let kind: CompletionItemKind = match definition.kind {
+                SymbolKind::FUNCTION => CompletionItemKind::FUNCTION,
+                SymbolKind::ENUM => CompletionItemKind::ENUM,
+                SymbolKind::STRUCT => CompletionItemKind::STRUCT,
+                _ => CompletionItemKind::CONSTANT,
+            };
+            completions.push(CompletionItem {
+                label: definition.name.value.clone(),
+                kind: Some(kind),
+                detail: signature,
+                ..Default::default()
+            });
+        }
+        for intrinsic in Intrinsic::iter() {
+            completions.push(CompletionItem {
+                label: format!("{intrinsic:?}").to_lowercase(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(intrinsic.stack_effect().to_string()),
+                ..Default::default()
+            });
+        }
+        for keyword in Keyword::iter() {
+            completions.push(CompletionItem {
+                label: format!("{keyword:?}").to_lowercase(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                ..Default::default()
+            });
+        }
+        completions
+    }
+
+    #[allow(deprecated)] // `DocumentSymbol::deprecated` has to be given
+    fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.definitions
+            .iter()
+            .map(|definition| DocumentSymbol {
+                name: definition.name.value.clone(),
+                detail: None,
+                kind: definition.kind,
+                tags: None,
+                deprecated: None,
+                range: Range::new(
+                    self.token_range(&definition.keyword).start,
+                    self.token_range(&definition.name).end,
+                ),
+                selection_range: self.token_range(&definition.name),
+                children: None,
+            })
+            .collect()
+    }
+
+    /// LSP position of the Rot row and byte column which start from one.
+    /// LSP positions start from zero and count the characters in UTF-16 code units.
+    fn position(&self, row: usize, column: usize) -> Position {
+        let line: &str = self
+            .lines
+            .get(row.saturating_sub(1))
+            .map_or("", String::as_str);
+        let prefix: &str = line.get(..column.saturating_sub(1)).unwrap_or(line);
+        Position::new(
+            row.saturating_sub(1) as u32,
+            prefix.encode_utf16().count() as u32,
+        )
+    }
+
+    fn token_range(&self, token: &Token) -> Range {
+        let location: &Location = &token.location;
+        // Columns after a newline in the token start from its last line
+        let rows: usize = token.value.matches('\n').count();
+        let end_column: usize = match token.value.rfind('\n') {
+            Some(newline) => token.value.len() - newline,
+            None => location.column + token.value.len(),
+        };
+        Range::new(
+            self.position(location.row, location.column),
+            self.position(location.row + rows, end_column),
+        )
+    }
+}
+
+/// Definitions are keywords `fun`, `const`, `enum` or `struct` followed by the name
+fn find_definitions(tokens: &[Token]) -> Vec<Definition> {
+    tokens
+        .windows(2)
+        .filter_map(|window| {
+            let kind: SymbolKind = match window[0].typ {
+                TokenType::Keyword(Keyword::Fun) => SymbolKind::FUNCTION,
+                TokenType::Keyword(Keyword::Const) => SymbolKind::CONSTANT,
+                TokenType::Keyword(Keyword::Enum) => SymbolKind::ENUM,
+                TokenType::Keyword(Keyword::Struct) => SymbolKind::STRUCT,
+                _ => return None,
+            };
+            if window[1].typ != TokenType::Identifier {
+                return None;
+            }
+            Some(Definition {
+                kind,
+                keyword: window[0].clone(),
+                name: window[1].clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::TEST_FOLDER;
+    use crate::data_types::DataType;
+
+    fn open_document(file: &str) -> Document {
+        let path = std::fs::canonicalize(format!("{TEST_FOLDER}/{file}")).unwrap();
+        let text: String = std::fs::read_to_string(&path).unwrap();
+        Document::new(&Url::from_file_path(path).unwrap(), text)
+    }
+
+    #[test]
+    fn lsp_diagnostics() {
+        let diagnostics: Vec<Diagnostic> = open_document("type_check_errors.rot").diagnostics();
+        assert_eq!(diagnostics.len(), 7);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 11), Position::new(1, 12))
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Invalid argument types for '+': int bool"
+        );
+        assert!(open_document("vm_control_flow.rot")
+            .diagnostics()
+            .is_empty());
+    }
+
+    #[test]
+    fn lsp_utf16_positions() {
+        // Columns of the lexer count bytes and LSP positions count UTF-16 code units
+        let text: String = "fun main() {\n    \"é😀\" 1 + drop\n}\n".to_string();
+        let document = Document::new(&Url::parse("file:///unicode.rot").unwrap(), text);
+        let diagnostics: Vec<Diagnostic> = document.diagnostics();
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 12), Position::new(1, 13))
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Invalid argument types for '+': str int"
+        );
+        let string: &Token = document
+            .tokens
+            .iter()
+            .find(|token| token.typ == TokenType::Literal(DataType::String))
+            .unwrap();
+        assert_eq!(
+            document.token_range(string),
+            Range::new(Position::new(1, 4), Position::new(1, 9))
+        );
+    }
+
+    #[test]
+    fn lsp_navigation() {
+        let document: Document = open_document("vm_control_flow.rot");
+        // `classify` is called on line 12 and defined on line 18
+        let call: Position = Position::new(11, 8);
+        assert_eq!(
+            document.definition(call),
+            Some(Range::new(Position::new(17, 4), Position::new(17, 12)))
+        );
+        let hover: HoverContents = document.hover(call).unwrap().contents;
+        assert_eq!(
+            hover,
+            HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: "```rot\nclassify : int ->\n```".to_string(),
+            })
+        );
+        let symbols: Vec<String> = document
+            .symbols()
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect();
+        assert_eq!(symbols, vec!["main", "classify"]);
+        let completions: Vec<String> = document
+            .completions()
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        assert!(["classify", "dup", "while"]
+            .iter()
+            .all(|label| completions.contains(&label.to_string())));
+    }
+}
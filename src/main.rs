@@ -1,23 +1,77 @@
+use std::io::Write;
+
 use clap::Parser;
 
 use cli::{CliAction, RotArgs};
-use compiler::compile_rot_file;
+use driver::{
+    check_rot_file, compile_rot_file, compile_rot_file_to_bytecode, format_rot_file, json_document,
+    run_rot_file, test_rot_file, OutputFormat,
+};
+use rot_lang::{run_lsp, run_repl, CompilerError};
 
-mod class;
 mod cli;
-mod compiler;
-mod constant;
-mod data_types;
-mod lexer;
+mod driver;
 
 fn main() {
-    cli_action(RotArgs::parse());
+    let args: RotArgs = RotArgs::parse();
+    let format: OutputFormat = args.format;
+    // Checking always reports its diagnostics so that an empty list means success
+    let is_check: bool = matches!(args.action, CliAction::Check(_));
+    let errors: Vec<CompilerError> = cli_action(args);
+    match format {
+        OutputFormat::Json if is_check || !errors.is_empty() => {
+            eprint!("{}", json_document("diagnostics", &errors))
+        }
+        OutputFormat::Json => {}
+        OutputFormat::Text => errors.iter().for_each(|error| eprintln!("{error}")),
+    }
+    if let Some(exit_code) = errors.iter().map(CompilerError::exit_code).max() {
+        std::process::exit(exit_code);
+    }
 }
 
-fn cli_action(args: RotArgs) {
-    match args.action {
+fn cli_action(args: RotArgs) -> Vec<CompilerError> {
+    let result: Result<(), CompilerError> = match args.action {
         // ./rot-rust compile <ROT_FILE>
-        // TODO: CompilerError handling
-        CliAction::Compile(target) => compile_rot_file(&target.rot_file, target.out).unwrap(),
-    }
+        CliAction::Compile(target) if target.bytecode => {
+            compile_rot_file_to_bytecode(&target.rot_file, target.out)
+        }
+        CliAction::Compile(target) => compile_rot_file(
+            &target.rot_file,
+            target.out,
+            &target.emit,
+            args.format,
+            target.save_asm,
+            target.verbose,
+            target.debug,
+        ),
+        // ./rot-rust run [--vm] [--debug] <ROT_FILE> [ARGS]...
+        CliAction::Run(target) => {
+            match run_rot_file(&target.rot_file, &target.args, target.vm, target.debug) {
+                // The exit code of the program is passed through without a diagnostic
+                Ok(exit_code) if exit_code != 0 => {
+                    let _ = std::io::stdout().flush();
+                    std::process::exit(exit_code);
+                }
+                result => result.map(|_| ()),
+            }
+        }
+        // ./rot-rust check <ROT_FILE>
+        CliAction::Check(target) => return check_rot_file(&target.rot_file),
+        // ./rot-rust test <ROT_FILE>
+        CliAction::Test(target) => test_rot_file(&target.rot_file),
+        // ./rot-rust fmt [--check] <ROT_FILES>...
+        CliAction::Fmt(target) => {
+            return target
+                .rot_files
+                .iter()
+                .filter_map(|rot_file| format_rot_file(rot_file, target.check).err())
+                .collect()
+        }
+        // ./rot-rust repl
+        CliAction::Repl => run_repl(),
+        // ./rot-rust lsp
+        CliAction::Lsp => run_lsp(),
+    };
+    result.err().into_iter().collect()
 }
@@ -0,0 +1,327 @@
+use std::io::{BufRead, Write};
+
+use crate::bytecode::{compile_bytecode, Bytecode};
+use crate::class::function::Function;
+use crate::class::intrinsic::intrinsic_from_string;
+use crate::class::program::Program;
+use crate::class::signature::Signature;
+use crate::class::statement::Statement;
+use crate::class::token::{Delimiter, Keyword, Token, TokenType};
+use crate::compiler::{parse_statements, parse_tokens, CompilerError};
+use crate::data_types::DataType;
+use crate::lexer::tokenize_code;
+use crate::type_checker::{check_types_defined, type_check_function, type_check_statements};
+use crate::vm::{Value, Vm};
+use crate::Compiler;
+
+/// Name of the function wrapping each evaluated line. It is not a valid
+/// identifier so it cannot clash with the user defined functions.
+const REPL_FUNCTION_NAME: &str = "<repl>";
+
+const REPL_HELP: &str = "\
+Enter Rot code to evaluate it or define functions with `fun`
+Commands:
+  :stack        Show the current stack
+  :clear        Remove all values from the stack
+  :type <word>  Show the type of a literal, function or intrinsic
+  :load <file>  Load the functions from a Rot file
+  :help         Show this help
+  :quit         Exit the REPL";
+
+#[derive(Debug, Default)]
+pub struct Repl {
+    program: Program,
+    vm: Vm,
+}
+
+pub fn run_repl() -> Result<(), CompilerError> {
+    let mut repl = Repl::default();
+    let mut stdout = std::io::stdout();
+    let mut input = String::new();
+    loop {
+        let prompt: &str = if input.is_empty() { "rot> " } else { "...> " };
+        print!("{prompt}");
+        stdout.flush().map_err(CompilerError::IOError)?;
+
+        let mut line = String::new();
+        let bytes_read: usize = std::io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(CompilerError::IOError)?;
+        if bytes_read == 0 {
+            println!();
+            return Ok(());
+        }
+        input.push_str(&line);
+        if !is_complete_input(&input) {
+            continue;
+        }
+
+        let input: String = std::mem::take(&mut input);
+        if input.trim() == ":quit" {
+            return Ok(());
+        }
+        if let Err(error) = repl.evaluate(input.trim(), &mut stdout) {
+            eprintln!("{error}");
+        }
+    }
+}
+
+/// Input is complete when every opened block has been closed
+fn is_complete_input(input: &str) -> bool {
+    let tokens: Vec<Token> = match tokenize_code(input, None) {
+        Ok(tokens) => tokens,
+        // Let the evaluation report the error
+        Err(_) => return true,
+    };
+    let mut depth: isize = 0;
+    for token in tokens {
+        match token.typ {
+            TokenType::Delimiter(Delimiter::OpenCurly)
+            | TokenType::Keyword(Keyword::If)
+            | TokenType::Keyword(Keyword::While)
+            | TokenType::Keyword(Keyword::Let) => depth += 1,
+            TokenType::Delimiter(Delimiter::CloseCurly)
+            | TokenType::Keyword(Keyword::Endif)
+            | TokenType::Keyword(Keyword::Done)
+            | TokenType::Keyword(Keyword::End) => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+impl Repl {
+    pub fn evaluate(&mut self, input: &str, output: &mut dyn Write) -> Result<(), CompilerError> {
+        if let Some(command) = input.strip_prefix(':') {
+            return self.command(command, output);
+        }
+        let tokens: Vec<Token> = tokenize_code(input, None)?;
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        if matches!(
+            tokens[0].typ,
+            TokenType::Keyword(Keyword::Fun | Keyword::Struct)
+        ) {
+            let items: Program = parse_tokens(&tokens)?;
+            self.define_items(items.clone())?;
+            for structure in &items.structs {
+                writeln!(
+                    output,
+                    "{} : {}",
+                    structure.name,
+                    structure.constructor_signature()
+                )
+                .map_err(CompilerError::IOError)?;
+            }
+            for function in &items.functions {
+                writeln!(output, "{} : {}", function.name, function.signature)
+                    .map_err(CompilerError::IOError)?;
+            }
+            return Ok(());
+        }
+
+        let statements: Vec<Statement> = parse_statements(&mut 0, &tokens, &[])?;
+        let stack_types: Vec<DataType> = self.vm.stack.iter().map(Value::data_type).collect();
+        type_check_statements(REPL_FUNCTION_NAME, &statements, &self.program, stack_types)?;
+        let mut program: Program = self.program.clone();
+        program.functions.push(Function {
+            name: REPL_FUNCTION_NAME.to_string(),
+            location: tokens[0].location.clone(),
+            signature: Signature {
+                parameters: Vec::new(),
+                return_type: Vec::new(),
+            },
+            tokens,
+            statements,
+        });
+        let bytecode: Bytecode = compile_bytecode(&program, REPL_FUNCTION_NAME)?;
+
+        // Failed evaluation should not leave the stack half modified
+        let stack: Vec<Value> = self.vm.stack.clone();
+        if let Err(error) = self.vm.execute(&bytecode, bytecode.entry, output) {
+            self.vm.stack = stack;
+            return Err(error);
+        }
+        self.print_stack(output)
+    }
+
+    fn command(&mut self, command: &str, output: &mut dyn Write) -> Result<(), CompilerError> {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "stack" => return self.print_stack(output),
+            "clear" => {
+                self.vm.stack.clear();
+                return self.print_stack(output);
+            }
+            "type" => return self.print_type(argument, output),
+            "load" => {
+                let items: Program = Compiler::new().add_file(argument)?.parse_definitions()?;
+                let count: usize = items.functions.len();
+                self.define_items(items)?;
+                writeln!(output, "Loaded {count} functions from '{argument}'")
+            }
+            "help" => writeln!(output, "{REPL_HELP}"),
+            _ => writeln!(output, "Unknown command ':{name}', type :help for help"),
+        }
+        .map_err(CompilerError::IOError)
+    }
+
+    /// Add new functions and structs replacing the previous definitions with the same name
+    fn define_items(&mut self, items: Program) -> Result<(), CompilerError> {
+        let mut defined: Program = self.program.clone();
+        for function in &items.functions {
+            defined
+                .functions
+                .retain(|defined| defined.name != function.name);
+            defined.functions.push(function.clone());
+        }
+        for structure in &items.structs {
+            defined
+                .structs
+                .retain(|defined| defined.name != structure.name);
+            defined.structs.push(structure.clone());
+        }
+        // Make sure that the new items compile before accepting them
+        for structure in &items.structs {
+            let types = structure.fields.iter().map(|field| &field.typ);
+            check_types_defined(types, &structure.location, &defined)?;
+        }
+        for function in &items.functions {
+            type_check_function(function, &defined)?;
+        }
+        if let Some(function) = items.functions.first() {
+            compile_bytecode(&defined, &function.name)?;
+        }
+        self.program = defined;
+        Ok(())
+    }
+
+    fn print_stack(&self, output: &mut dyn Write) -> Result<(), CompilerError> {
+        let values: Vec<String> = self
+            .vm
+            .stack
+            .iter()
+            .map(|value| format!("{}: {}", format_value(value), value.data_type()))
+            .collect();
+        writeln!(output, "[{}]", values.join(", ")).map_err(CompilerError::IOError)
+    }
+
+    fn print_type(&self, word: &str, output: &mut dyn Write) -> Result<(), CompilerError> {
+        if let Some(intrinsic) = intrinsic_from_string(word) {
+            return writeln!(output, "{word} : {}", intrinsic.stack_effect())
+                .map_err(CompilerError::IOError);
+        }
+        if let Some(function) = self.program.function(word) {
+            return writeln!(output, "{word} : {}", function.signature)
+                .map_err(CompilerError::IOError);
+        }
+        if let Some(structure) = self.program.structure(word) {
+            return writeln!(output, "{word} : {}", structure.constructor_signature())
+                .map_err(CompilerError::IOError);
+        }
+        let tokens: Vec<Token> = tokenize_code(word, None)?;
+        match tokens.as_slice() {
+            [Token {
+                typ: TokenType::Literal(data_type),
+                ..
+            }] => writeln!(output, "{word} : {data_type}"),
+            _ => writeln!(output, "Unknown word '{word}'"),
+        }
+        .map_err(CompilerError::IOError)
+    }
+}
+
+/// Show strings and characters quoted so that they are distinguishable on the stack
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Character(character) => format!("{character:?}"),
+        Value::String(string) => format!("{string:?}"),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::TEST_FOLDER;
+
+    fn evaluate(repl: &mut Repl, input: &str) -> String {
+        let mut output: Vec<u8> = Vec::new();
+        repl.evaluate(input, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn repl_persistent_stack() {
+        let mut repl = Repl::default();
+        assert_eq!(evaluate(&mut repl, "34 35"), "[34: int, 35: int]\n");
+        assert_eq!(evaluate(&mut repl, "+ dup print"), "69\n[69: int]\n");
+        assert_eq!(
+            evaluate(&mut repl, "'c' \"str\""),
+            "[69: int, 'c': char, \"str\": str]\n"
+        );
+        assert_eq!(evaluate(&mut repl, ":clear"), "[]\n");
+        // Failed evaluation keeps the stack intact
+        evaluate(&mut repl, "1 2");
+        assert!(repl.evaluate("3 0 /", &mut Vec::new()).is_err());
+        assert!(repl.evaluate("true +", &mut Vec::new()).is_err());
+        assert_eq!(evaluate(&mut repl, ":stack"), "[1: int, 2: int]\n");
+    }
+
+    #[test]
+    fn repl_functions() {
+        let mut repl = Repl::default();
+        assert_eq!(
+            evaluate(&mut repl, "fun square(n: int) -> int { n n * }"),
+            "square : int -> int\n"
+        );
+        assert_eq!(evaluate(&mut repl, "7 square"), "[49: int]\n");
+        assert_eq!(evaluate(&mut repl, ":type square"), "square : int -> int\n");
+        assert_eq!(evaluate(&mut repl, ":type swap"), "swap : a b -> b a\n");
+        assert_eq!(evaluate(&mut repl, ":type true"), "true : bool\n");
+        assert_eq!(
+            evaluate(
+                &mut repl,
+                &format!(":load {TEST_FOLDER}/vm_control_flow.rot")
+            ),
+            format!("Loaded 2 functions from '{TEST_FOLDER}/vm_control_flow.rot'\n")
+        );
+        assert_eq!(evaluate(&mut repl, "1000 classify"), "large\n[49: int]\n");
+        assert!(repl
+            .evaluate("fun broken() { unknown }", &mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn repl_structs() {
+        let mut repl = Repl::default();
+        assert_eq!(
+            evaluate(&mut repl, "struct Point { x: int, y: int }"),
+            "Point : int int -> Point\n"
+        );
+        assert_eq!(evaluate(&mut repl, "3 4 Point .y"), "[4: int]\n");
+        assert_eq!(
+            evaluate(&mut repl, ":type Point"),
+            "Point : int int -> Point\n"
+        );
+        assert!(repl
+            .evaluate("struct Line { start: Piont }", &mut Vec::new())
+            .is_err());
+        assert_eq!(evaluate(&mut repl, ":type Line"), "Unknown word 'Line'\n");
+    }
+
+    #[test]
+    fn repl_multiline_input() {
+        assert!(!is_complete_input("fun f() {"));
+        assert!(!is_complete_input("fun f() {\n  if true do"));
+        assert!(is_complete_input("fun f() {\n  if true do 1 endif\n}"));
+        assert!(!is_complete_input("1 let x in"));
+        assert!(is_complete_input("1 let x in x end"));
+        assert!(is_complete_input("1 2 +"));
+    }
+}
@@ -0,0 +1,43 @@
+/// Prefix of the include paths which refer to the standard library
+pub const STANDARD_LIBRARY_PREFIX: &str = "std/";
+
+/// Rot files of the standard library embedded in the compiler so that
+/// they can be included without them being on the disk
+const STANDARD_LIBRARY: &[(&str, &str)] = &[
+    ("std/alloc.rot", include_str!("../std/alloc.rot")),
+    ("std/format.rot", include_str!("../std/format.rot")),
+    ("std/io.rot", include_str!("../std/io.rot")),
+    ("std/linux.rot", include_str!("../std/linux.rot")),
+    ("std/memory.rot", include_str!("../std/memory.rot")),
+    ("std/string.rot", include_str!("../std/string.rot")),
+];
+
+/// Code of the standard library file, e.g. `std/linux.rot`
+pub fn standard_library_file(path: &str) -> Option<&'static str> {
+    STANDARD_LIBRARY
+        .iter()
+        .find(|(file, _)| *file == path)
+        .map(|(_, code)| *code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::type_checker::type_check_functions;
+    use crate::Compiler;
+
+    #[test]
+    fn standard_library_type_checks() {
+        for (path, _) in STANDARD_LIBRARY {
+            let program = Compiler::new()
+                .add_source("main.rot", &format!("include \"{path}\""))
+                .parse_definitions()
+                .unwrap();
+            let errors: Vec<String> = type_check_functions(&program)
+                .iter()
+                .map(|error| error.to_string())
+                .collect();
+            assert_eq!(errors, Vec::<String>::new(), "{path}");
+        }
+    }
+}
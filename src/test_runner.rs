@@ -0,0 +1,148 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::bytecode::{compile_bytecode, Bytecode};
+use crate::class::function::Function;
+use crate::class::program::Program;
+use crate::compiler::CompilerError;
+use crate::vm::Vm;
+
+/// Functions whose name starts with the prefix are run by `rot test`
+pub const TEST_FUNCTION_PREFIX: &str = "test_";
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub duration: Duration,
+    /// Output of the test function
+    pub output: String,
+    pub error: Option<CompilerError>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+pub fn is_test_function(function: &Function) -> bool {
+    function.name.starts_with(TEST_FUNCTION_PREFIX)
+}
+
+/// Run every test function in its own virtual machine and report the results
+pub fn run_tests(
+    program: &Program,
+    output: &mut dyn Write,
+) -> Result<Vec<TestResult>, CompilerError> {
+    let tests: Vec<&Function> = program
+        .functions
+        .iter()
+        .filter(|function| is_test_function(function))
+        .collect();
+    for test in &tests {
+        let signature = &test.signature;
+        if !signature.parameters.is_empty() || !signature.return_type.is_empty() {
+            return Err(CompilerError::TypeError(
+                Some(test.location.clone()),
+                format!(
+                    "Test function '{}' should not take parameters or return values \
+                    but has the signature {signature}",
+                    test.name
+                ),
+            ));
+        }
+    }
+
+    let start: Instant = Instant::now();
+    writeln!(output, "running {} tests", tests.len()).map_err(CompilerError::IOError)?;
+    let mut results: Vec<TestResult> = Vec::new();
+    for test in tests {
+        let bytecode: Bytecode = compile_bytecode(program, &test.name)?;
+        let mut test_output: Vec<u8> = Vec::new();
+        let test_start: Instant = Instant::now();
+        let error: Option<CompilerError> = Vm::default()
+            .execute(&bytecode, bytecode.entry, &mut test_output)
+            .err();
+        let result = TestResult {
+            name: test.name.clone(),
+            duration: test_start.elapsed(),
+            output: String::from_utf8_lossy(&test_output).to_string(),
+            error,
+        };
+        let status: &str = if result.passed() { "ok" } else { "FAILED" };
+        writeln!(
+            output,
+            "test {} ... {status} ({:.2?})",
+            result.name, result.duration
+        )
+        .map_err(CompilerError::IOError)?;
+        results.push(result);
+    }
+
+    let failures: Vec<&TestResult> = results.iter().filter(|result| !result.passed()).collect();
+    for failure in &failures {
+        writeln!(output, "\n---- {} ----", failure.name).map_err(CompilerError::IOError)?;
+        write!(output, "{}", failure.output).map_err(CompilerError::IOError)?;
+        if let Some(error) = &failure.error {
+            writeln!(output, "{error}").map_err(CompilerError::IOError)?;
+        }
+    }
+    let status: &str = if failures.is_empty() { "ok" } else { "FAILED" };
+    writeln!(
+        output,
+        "\ntest result: {status}. {} passed; {} failed; finished in {:.2?}",
+        results.len() - failures.len(),
+        failures.len(),
+        start.elapsed()
+    )
+    .map_err(CompilerError::IOError)?;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::TEST_FOLDER, Compiler};
+
+    #[test]
+    fn run_test_functions() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_file(&format!("{TEST_FOLDER}/test_functions.rot"))
+            .unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        let results: Vec<TestResult> = compiler.test(&mut output).unwrap();
+        let summary: Vec<(&str, bool)> = results
+            .iter()
+            .map(|result| (result.name.as_str(), result.passed()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("test_addition", true),
+                ("test_failing", false),
+                ("test_loop", true)
+            ]
+        );
+        assert_eq!(results[1].output, "5\n");
+        assert_eq!(
+            results[1].error.as_ref().unwrap().to_string(),
+            format!("Runtime error: Assertion failed\n  at test_failing ({TEST_FOLDER}/test_functions.rot:11:18)")
+        );
+        let output: String = String::from_utf8(output).unwrap();
+        assert!(output.contains("test result: FAILED. 2 passed; 1 failed"));
+
+        // Test functions cannot take parameters or return values
+        let result = Compiler::new()
+            .add_source(
+                "main.rot",
+                "fun main() {}\nfun test_parameter(a: int) -> int { a }",
+            )
+            .test(&mut Vec::new());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Type error: main.rot:2:5: Test function 'test_parameter' should not take parameters \
+            or return values but has the signature int -> int"
+        );
+    }
+}
@@ -0,0 +1,1390 @@
+use crate::class::expression::{Expression, ExpressionType};
+use crate::class::function::Function;
+use strum::IntoEnumIterator;
+
+use crate::class::intrinsic::{intrinsic_from_string, Intrinsic};
+use crate::class::location::Location;
+use crate::class::program::{Memory, Program};
+use crate::class::signature::{Parameter, Signature};
+use crate::class::statement::{Statement, StatementType};
+use crate::class::token::{BinaryOperator, UnaryOperator};
+use crate::compiler::CompilerError;
+use crate::constant::MAIN_FUNCTION_NAME;
+use crate::data_types::{DataType, IntegerType};
+
+/// Types of the values on the stack or `None` if the code is unreachable,
+/// e.g. after `return`, `break` or `continue`
+type TypeStack = Option<Vec<DataType>>;
+
+struct LoopTypes {
+    /// Stack before the loop condition, expected by `continue`
+    start: Vec<DataType>,
+    /// Stack after the loop condition, expected by `break`
+    end: Vec<DataType>,
+}
+
+struct TypeChecker<'a> {
+    program: &'a Program,
+    name: &'a str,
+    return_type: &'a [DataType],
+    loops: Vec<LoopTypes>,
+    /// Local variables in scope. Later ones shadow the earlier ones with the same name.
+    locals: Vec<(String, DataType)>,
+}
+
+/// Type check every function and return all found errors
+pub fn type_check_functions(program: &Program) -> Vec<CompilerError> {
+    let mut errors: Vec<CompilerError> = Vec::new();
+    if let Some(main) = program.function(MAIN_FUNCTION_NAME) {
+        errors.extend(check_main_signature(main).err());
+    }
+    for structure in &program.structs {
+        let types = structure.fields.iter().map(|field| &field.typ);
+        errors.extend(check_types_defined(types, &structure.location, program).err());
+    }
+    for memory in &program.memories {
+        errors.extend(check_memory(memory, program).err());
+    }
+    for function in &program.functions {
+        let signature: &Signature = &function.signature;
+        let types = signature.parameters.iter().map(|parameter| &parameter.typ);
+        let types = types.chain(signature.return_type.iter());
+        if let Err(error) = check_types_defined(types, &function.location, program) {
+            errors.push(error);
+            continue;
+        }
+        errors.extend(type_check_function(function, program).err());
+    }
+    errors
+}
+
+/// Custom types should refer to defined structs and arrays should hold bool, char or integers.
+/// Pointers are checked by the type they point to.
+pub fn check_types_defined<'a>(
+    types: impl Iterator<Item = &'a DataType>,
+    location: &Location,
+    program: &Program,
+) -> Result<(), CompilerError> {
+    for mut typ in types {
+        while let DataType::Pointer(Some(pointee)) = typ {
+            typ = pointee;
+        }
+        let name: &String = match typ {
+            DataType::Custom(name) => name,
+            DataType::Array(element, _) => match **element {
+                DataType::Boolean
+                | DataType::Character
+                | DataType::Integer
+                | DataType::SizedInteger(_) => continue,
+                _ => {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!("Arrays can only hold bool, char or integers but got {element}"),
+                    ))
+                }
+            },
+            _ => continue,
+        };
+        if program.structure(name).is_some() {
+            continue;
+        }
+        let mut error: String = format!("Unknown type '{name}'");
+        let names = program
+            .structs
+            .iter()
+            .map(|structure| structure.name.clone());
+        if let Some(suggestion) = closest_name(name, names) {
+            error.push_str(&format!(", did you mean '{suggestion}'?"));
+        }
+        return Err(CompilerError::TypeError(Some(location.clone()), error));
+    }
+    Ok(())
+}
+
+/// Memories are only used for arrays
+fn check_memory(memory: &Memory, program: &Program) -> Result<(), CompilerError> {
+    check_types_defined([&memory.typ].into_iter(), &memory.location, program)?;
+    if !matches!(memory.typ, DataType::Array(..)) {
+        return Err(CompilerError::TypeError(
+            Some(memory.location.clone()),
+            format!(
+                "Memory '{}' should be an array but has the type {}",
+                memory.name, memory.typ
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The program starts from `main` which can take the command-line arguments
+/// as `argc: int, argv: ptr` and return the exit code as `int`
+fn check_main_signature(main: &Function) -> Result<(), CompilerError> {
+    let parameters: Vec<&DataType> = main
+        .signature
+        .parameters
+        .iter()
+        .map(|parameter| &parameter.typ)
+        .collect();
+    let valid_parameters: bool = matches!(
+        parameters[..],
+        [] | [DataType::Integer, DataType::Pointer(_)]
+    );
+    let valid_return: bool = matches!(main.signature.return_type[..], [] | [DataType::Integer]);
+    if valid_parameters && valid_return {
+        return Ok(());
+    }
+    Err(CompilerError::TypeError(
+        Some(main.location.clone()),
+        format!(
+            "Function '{MAIN_FUNCTION_NAME}' should take no parameters or 'argc: int, argv: ptr' \
+            and return nothing or int but has the signature {}",
+            main.signature
+        ),
+    ))
+}
+
+/// Make sure that the function body turns its parameters into its return values.
+/// Parameters are popped from the stack of the caller into local variables.
+pub fn type_check_function(function: &Function, program: &Program) -> Result<(), CompilerError> {
+    let mut checker = TypeChecker {
+        program,
+        name: &function.name,
+        return_type: &function.signature.return_type,
+        loops: Vec::new(),
+        locals: Vec::new(),
+    };
+    for parameter in &function.signature.parameters {
+        let is_bound: bool = checker
+            .locals
+            .iter()
+            .any(|(name, _)| *name == parameter.name);
+        checker.check_local_name(&parameter.name, is_bound, &parameter.location)?;
+        checker
+            .locals
+            .push((parameter.name.clone(), parameter.typ.clone()));
+    }
+    let location: Location = match function.tokens.last() {
+        Some(token) => token.location.clone(),
+        None => function.location.clone(),
+    };
+    if let Some(stack) = checker.check_statements(&function.statements, Some(Vec::new()))? {
+        checker.check_return(&stack, &location)?;
+    }
+    Ok(())
+}
+
+/// Find the code which is valid but likely a mistake, e.g. unused parameters
+pub fn find_warnings(program: &Program) -> Vec<CompilerError> {
+    let mut warnings: Vec<CompilerError> = Vec::new();
+    for function in &program.functions {
+        let mut scope: Vec<(&str, Option<usize>)> = function
+            .signature
+            .parameters
+            .iter()
+            .enumerate()
+            .map(|(i, parameter)| (parameter.name.as_str(), Some(i)))
+            .collect();
+        let mut used: Vec<bool> = vec![false; scope.len()];
+        for statement in &function.statements {
+            mark_used_parameters(statement, &mut scope, &mut used);
+        }
+        for (parameter, used) in function.signature.parameters.iter().zip(used) {
+            if !used {
+                warnings.push(CompilerError::Warning(
+                    Some(parameter.location.clone()),
+                    format!(
+                        "Parameter '{}' of '{}' is never used",
+                        parameter.name, function.name
+                    ),
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Mark the parameters referred to by the statement. The scope holds the local
+/// variables with the index of the parameter or `None` for the bound variables.
+fn mark_used_parameters<'a>(
+    statement: &'a Statement,
+    scope: &mut Vec<(&'a str, Option<usize>)>,
+    used: &mut [bool],
+) {
+    let length: usize = scope.len();
+    if let Some(expression) = &statement.expression {
+        mark_used_parameters_in(expression, scope, used);
+    }
+    for child in statement.statements.iter().flatten() {
+        if child.typ == StatementType::Variable {
+            scope.push((child.value.as_deref().unwrap_or_default(), None));
+        } else {
+            mark_used_parameters(child, scope, used);
+        }
+    }
+    scope.truncate(length);
+}
+
+fn mark_used_parameters_in(
+    expression: &Expression,
+    scope: &[(&str, Option<usize>)],
+    used: &mut [bool],
+) {
+    if expression.typ == ExpressionType::Identifier {
+        let name: &str = expression.value.as_deref().unwrap_or_default();
+        if let Some((_, Some(parameter))) = scope.iter().rev().find(|(local, _)| *local == name) {
+            used[*parameter] = true;
+        }
+    }
+    for child in expression.expressions.iter().flatten() {
+        mark_used_parameters_in(child, scope, used);
+    }
+}
+
+/// Type check statements of the function called `name` starting from `stack`
+/// and return the resulting stack
+pub fn type_check_statements(
+    name: &str,
+    statements: &[Statement],
+    program: &Program,
+    stack: Vec<DataType>,
+) -> Result<Vec<DataType>, CompilerError> {
+    let mut checker = TypeChecker {
+        program,
+        name,
+        return_type: &[],
+        loops: Vec::new(),
+        locals: Vec::new(),
+    };
+    Ok(checker
+        .check_statements(statements, Some(stack))?
+        .unwrap_or_default())
+}
+
+impl TypeChecker<'_> {
+    fn check_statements(
+        &mut self,
+        statements: &[Statement],
+        mut stack: TypeStack,
+    ) -> Result<TypeStack, CompilerError> {
+        for statement in statements {
+            stack = match stack {
+                Some(stack) => self.check_statement(statement, stack)?,
+                // Unreachable code is not type checked
+                None => return Ok(None),
+            };
+        }
+        Ok(stack)
+    }
+
+    fn check_block(
+        &mut self,
+        block: &Statement,
+        stack: TypeStack,
+    ) -> Result<TypeStack, CompilerError> {
+        match &block.statements {
+            Some(statements) => self.check_statements(statements, stack),
+            None => Ok(stack),
+        }
+    }
+
+    fn check_statement(
+        &mut self,
+        statement: &Statement,
+        mut stack: Vec<DataType>,
+    ) -> Result<TypeStack, CompilerError> {
+        let location: &Location = &statement.location;
+        match &statement.typ {
+            StatementType::Expression => {
+                if let Some(expression) = &statement.expression {
+                    self.check_expression(expression, &mut stack)?;
+                }
+                Ok(Some(stack))
+            }
+            StatementType::Compound => self.check_block(statement, Some(stack)),
+            StatementType::Binding => {
+                let (body, variables) =
+                    statement.statements.as_ref().unwrap().split_last().unwrap();
+                let types: Vec<DataType> =
+                    pop_arguments(&mut stack, variables.len(), "let", location)?;
+                let scope: usize = self.locals.len();
+                for (i, (variable, typ)) in variables.iter().zip(types).enumerate() {
+                    let name: &str = variable.value.as_deref().unwrap_or_default();
+                    let is_bound: bool = variables[..i]
+                        .iter()
+                        .any(|bound| bound.value == variable.value);
+                    self.check_local_name(name, is_bound, &variable.location)?;
+                    self.locals.push((name.to_string(), typ));
+                }
+                let stack: Result<TypeStack, CompilerError> = self.check_block(body, Some(stack));
+                self.locals.truncate(scope);
+                stack
+            }
+            StatementType::Conditional => {
+                let branches: &Vec<Statement> = statement.statements.as_ref().unwrap();
+                let stack: Vec<DataType> = match self.check_condition(&branches[0], stack, "if")? {
+                    Some(stack) => stack,
+                    None => return Ok(None),
+                };
+                let then_stack: TypeStack = self.check_block(&branches[1], Some(stack.clone()))?;
+                let else_stack: TypeStack = match branches.get(2) {
+                    Some(else_branch) => self.check_statement(else_branch, stack)?,
+                    None => Some(stack),
+                };
+                match (then_stack, else_stack) {
+                    (Some(then_stack), Some(else_stack)) if then_stack != else_stack => {
+                        Err(CompilerError::TypeError(
+                            Some(location.clone()),
+                            format!(
+                                "Branches of 'if' leave different types on the stack: {} and {}",
+                                format_types(&then_stack),
+                                format_types(&else_stack)
+                            ),
+                        ))
+                    }
+                    (Some(stack), _) | (None, Some(stack)) => Ok(Some(stack)),
+                    (None, None) => Ok(None),
+                }
+            }
+            StatementType::Loop => {
+                let branches: &Vec<Statement> = statement.statements.as_ref().unwrap();
+                let start: Vec<DataType> = stack.clone();
+                let end: Vec<DataType> = match self.check_condition(&branches[0], stack, "while")? {
+                    Some(stack) => stack,
+                    None => return Ok(None),
+                };
+                self.loops.push(LoopTypes {
+                    start: start.clone(),
+                    end: end.clone(),
+                });
+                let body_stack: TypeStack = self.check_block(&branches[1], Some(end.clone()))?;
+                self.loops.pop();
+                match body_stack {
+                    Some(body_stack) if body_stack != start => Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!(
+                            "Loop should not change the types on the stack: expected {} but got {}",
+                            format_types(&start),
+                            format_types(&body_stack)
+                        ),
+                    )),
+                    _ => Ok(Some(end)),
+                }
+            }
+            StatementType::Break | StatementType::Continue => {
+                let keyword: &str = match statement.typ {
+                    StatementType::Break => "break",
+                    _ => "continue",
+                };
+                let expected: &Vec<DataType> = match (self.loops.last(), &statement.typ) {
+                    (Some(labels), StatementType::Break) => &labels.end,
+                    (Some(labels), _) => &labels.start,
+                    (None, _) => {
+                        return Err(CompilerError::TypeError(
+                            Some(location.clone()),
+                            format!("'{keyword}' outside of a loop"),
+                        ))
+                    }
+                };
+                if stack != *expected {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!(
+                            "'{keyword}' expects {} on the stack but got {}",
+                            format_types(expected),
+                            format_types(&stack)
+                        ),
+                    ));
+                }
+                Ok(None)
+            }
+            StatementType::Return => {
+                self.check_return(&stack, location)?;
+                Ok(None)
+            }
+            StatementType::NoOperation => Ok(Some(stack)),
+            typ => Err(CompilerError::TypeError(
+                Some(location.clone()),
+                format!("Statement {typ:?} is not supported"),
+            )),
+        }
+    }
+
+    /// Condition block should push a boolean which is consumed by the branch
+    fn check_condition(
+        &mut self,
+        condition: &Statement,
+        stack: Vec<DataType>,
+        keyword: &str,
+    ) -> Result<TypeStack, CompilerError> {
+        let mut stack: Vec<DataType> = match self.check_block(condition, Some(stack))? {
+            Some(stack) => stack,
+            None => return Ok(None),
+        };
+        match stack.pop() {
+            Some(DataType::Boolean) => Ok(Some(stack)),
+            typ => Err(CompilerError::TypeError(
+                Some(condition.location.clone()),
+                format!(
+                    "Condition of '{keyword}' should leave bool on the stack but got {}",
+                    typ.map(|typ| typ.to_string())
+                        .unwrap_or("nothing".to_string())
+                ),
+            )),
+        }
+    }
+
+    fn check_return(&self, stack: &[DataType], location: &Location) -> Result<(), CompilerError> {
+        if stack != self.return_type {
+            return Err(CompilerError::TypeError(
+                Some(location.clone()),
+                format!(
+                    "Expected '{}' to return {} but the stack has {}",
+                    self.name,
+                    format_types(self.return_type),
+                    format_types(stack)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_expression(
+        &mut self,
+        expression: &Expression,
+        stack: &mut Vec<DataType>,
+    ) -> Result<(), CompilerError> {
+        let location: &Location = &expression.location;
+        let word: &str = expression.value.as_deref().unwrap_or_default();
+        match &expression.typ {
+            ExpressionType::Literal(data_type) => {
+                let typ: Option<IntegerType> = data_type.integer_type();
+                if typ.is_some_and(|typ| typ.parse_literal(word).is_none()) {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!("Integer literal '{word}' is out of range for {data_type}"),
+                    ));
+                }
+                stack.push(data_type.clone());
+            }
+            ExpressionType::Binary(BinaryOperator::Assignment) => {
+                match expression.expressions.as_deref() {
+                    Some([variable]) if variable.typ == ExpressionType::Identifier => {
+                        let name: &str = variable.value.as_deref().unwrap_or_default();
+                        let Some(local) = self.local(name).cloned() else {
+                            return Err(CompilerError::TypeError(
+                                Some(variable.location.clone()),
+                                format!("Cannot assign to '{name}' which is not a local variable"),
+                            ));
+                        };
+                        let value: DataType = pop_arguments(stack, 1, word, location)?.remove(0);
+                        if value != local {
+                            return Err(CompilerError::TypeError(
+                                Some(location.clone()),
+                                format!(
+                                    "Cannot assign {} to the local variable '{}' of type {}",
+                                    value, name, local
+                                ),
+                            ));
+                        }
+                    }
+                    Some([index]) => {
+                        let element: DataType = self.check_index(index, stack)?;
+                        let value: DataType = pop_arguments(stack, 1, word, location)?.remove(0);
+                        if value != element {
+                            return Err(CompilerError::TypeError(
+                                Some(location.clone()),
+                                format!("Cannot assign {value} to an element of type {element}"),
+                            ));
+                        }
+                    }
+                    _ => {
+                        return Err(CompilerError::TypeError(
+                            Some(location.clone()),
+                            "Only elements of arrays and local variables can be assigned, \
+                            e.g. 'values[0] ='"
+                                .to_string(),
+                        ))
+                    }
+                }
+            }
+            ExpressionType::Binary(operator) => {
+                let arguments: Vec<DataType> = pop_arguments(stack, 2, word, location)?;
+                match binary_operation_type(operator, &arguments[0], &arguments[1]) {
+                    Some(data_type) => stack.push(data_type),
+                    None => {
+                        return Err(CompilerError::TypeError(
+                            Some(location.clone()),
+                            format!(
+                                "Invalid argument types for '{word}': {}",
+                                format_types(&arguments)
+                            ),
+                        ))
+                    }
+                }
+            }
+            ExpressionType::Unary(operator) => {
+                let argument: Vec<DataType> = pop_arguments(stack, 1, word, location)?;
+                match unary_operation_type(operator, &argument[0]) {
+                    Some(data_type) => stack.push(data_type),
+                    None => {
+                        return Err(CompilerError::TypeError(
+                            Some(location.clone()),
+                            format!("Invalid argument type for '{word}': {}", argument[0]),
+                        ))
+                    }
+                }
+            }
+            ExpressionType::Identifier | ExpressionType::FunctionCall => {
+                if expression.typ == ExpressionType::FunctionCall {
+                    self.check_call_arguments(expression, stack)?;
+                }
+                if let Some(local) = self.local(word) {
+                    stack.push(local.clone());
+                } else if let Some(intrinsic) = intrinsic_from_string(word) {
+                    check_intrinsic(intrinsic, stack, word, location)?;
+                } else if let Some(function) = self.program.function(word) {
+                    let parameters: Vec<DataType> = function
+                        .signature
+                        .parameters
+                        .iter()
+                        .map(|parameter| parameter.typ.clone())
+                        .collect();
+                    check_arguments(stack, &parameters, word, location)?;
+                    stack.extend(function.signature.return_type.iter().cloned());
+                } else if let Some(structure) = self.program.structure(word) {
+                    // Struct is constructed from its fields in the order they are declared
+                    let fields: Vec<DataType> = structure
+                        .fields
+                        .iter()
+                        .map(|field| field.typ.clone())
+                        .collect();
+                    check_arguments(stack, &fields, word, location)?;
+                    stack.push(DataType::Custom(structure.name.clone()));
+                } else if let Some((_, memory)) = self.program.memory(word) {
+                    stack.push(memory.typ.clone());
+                } else {
+                    return Err(self.unknown_word(word, location));
+                }
+            }
+            ExpressionType::FieldAccess => {
+                for expression in expression.expressions.iter().flatten() {
+                    self.check_expression(expression, stack)?;
+                }
+                let typ: DataType =
+                    pop_arguments(stack, 1, &format!(".{word}"), location)?.remove(0);
+                let structure = match &typ {
+                    DataType::Custom(name) => self.program.structure(name),
+                    _ => None,
+                };
+                let Some(structure) = structure else {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!("Expected a struct for the field '{word}' but got {typ}"),
+                    ));
+                };
+                match structure.field(word) {
+                    Some((_, field)) => stack.push(field.typ.clone()),
+                    None => {
+                        return Err(CompilerError::TypeError(
+                            Some(location.clone()),
+                            format!("Struct '{}' has no field '{word}'", structure.name),
+                        ))
+                    }
+                }
+            }
+            ExpressionType::Cast(typ) => {
+                check_types_defined([typ].into_iter(), location, self.program)?;
+                let value: DataType = pop_arguments(stack, 1, "cast", location)?.remove(0);
+                if !is_valid_cast(&value, typ) {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!("Cannot cast {value} to {typ}"),
+                    ));
+                }
+                stack.push(typ.clone());
+            }
+            ExpressionType::Index => {
+                let element: DataType = self.check_index(expression, stack)?;
+                stack.push(element);
+            }
+            ExpressionType::Enclosure => {
+                for expression in expression.expressions.iter().flatten() {
+                    self.check_expression(expression, stack)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Push the arguments of the call to the function called `value` of the expression.
+    /// Each argument is checked on its own and has to push one value of the parameter type.
+    fn check_call_arguments(
+        &mut self,
+        expression: &Expression,
+        stack: &mut Vec<DataType>,
+    ) -> Result<(), CompilerError> {
+        let location: &Location = &expression.location;
+        let word: &str = expression.value.as_deref().unwrap_or_default();
+        let Some(function) = self.program.function(word) else {
+            let is_defined: bool = self.local(word).is_some()
+                || intrinsic_from_string(word).is_some()
+                || self.program.item_location(word).is_some();
+            return Err(match is_defined {
+                true => CompilerError::TypeError(
+                    Some(location.clone()),
+                    format!(
+                        "Only functions can be called with arguments \
+                        but '{word}' is not a function"
+                    ),
+                ),
+                false => self.unknown_word(word, location),
+            });
+        };
+        let arguments: &[Expression] = expression.expressions.as_deref().unwrap_or_default();
+        let parameters: &[Parameter] = &function.signature.parameters;
+        if arguments.len() != parameters.len() {
+            return Err(CompilerError::TypeError(
+                Some(location.clone()),
+                format!(
+                    "Wrong number of arguments for '{word}': expected {} but got {}",
+                    parameters.len(),
+                    arguments.len()
+                ),
+            ));
+        }
+        for (i, (argument, parameter)) in arguments.iter().zip(parameters).enumerate() {
+            let mut argument_stack: Vec<DataType> = Vec::new();
+            self.check_expression(argument, &mut argument_stack)?;
+            if argument_stack != [parameter.typ.clone()] {
+                return Err(CompilerError::TypeError(
+                    Some(argument.location.clone()),
+                    format!(
+                        "Argument {} of '{word}' should push {} but pushes {}",
+                        i + 1,
+                        parameter.typ,
+                        format_types(&argument_stack)
+                    ),
+                ));
+            }
+            stack.append(&mut argument_stack);
+        }
+        Ok(())
+    }
+
+    /// Check the array and the index of the `Index` expression and pop them from the stack.
+    /// Returns the type of the element.
+    fn check_index(
+        &mut self,
+        expression: &Expression,
+        stack: &mut Vec<DataType>,
+    ) -> Result<DataType, CompilerError> {
+        let location: &Location = &expression.location;
+        let children: &[Expression] = expression.expressions.as_deref().unwrap_or_default();
+        for child in children {
+            self.check_expression(child, stack)?;
+        }
+        let types: Vec<DataType> = pop_arguments(stack, 2, "[]", location)?;
+        let DataType::Array(element, length) = &types[0] else {
+            return Err(CompilerError::TypeError(
+                Some(location.clone()),
+                format!("Expected an array to index but got {}", types[0]),
+            ));
+        };
+        if types[1] != DataType::Integer {
+            return Err(CompilerError::TypeError(
+                Some(location.clone()),
+                format!("Index should be int but got {}", types[1]),
+            ));
+        }
+        // Constant indices are checked at compile time
+        let index: Option<&[Expression]> = children
+            .last()
+            .and_then(|index| index.expressions.as_deref());
+        if let Some([constant]) = index {
+            let value: &str = constant.value.as_deref().unwrap_or_default();
+            let is_integer: bool = constant.typ == ExpressionType::Literal(DataType::Integer);
+            let index: Option<i64> = IntegerType::I64.parse_literal(value);
+            if is_integer && index.is_none_or(|index| index as usize >= *length) {
+                return Err(CompilerError::TypeError(
+                    Some(constant.location.clone()),
+                    format!("Index {value} is out of bounds for {}", types[0]),
+                ));
+            }
+        }
+        Ok(*element.clone())
+    }
+
+    /// Type of the innermost local variable called `name`
+    fn local(&self, name: &str) -> Option<&DataType> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|(local, _)| local == name)
+            .map(|(_, typ)| typ)
+    }
+
+    /// Local variables can shadow each other but not the words of the program.
+    /// `is_bound` tells if an earlier variable of the same binding has the name.
+    fn check_local_name(
+        &self,
+        name: &str,
+        is_bound: bool,
+        location: &Location,
+    ) -> Result<(), CompilerError> {
+        let error: Option<String> = if intrinsic_from_string(name).is_some() {
+            Some(format!(
+                "Local variable '{name}' cannot have the name of an intrinsic"
+            ))
+        } else if let Some(defined) = self.program.item_location(name) {
+            Some(format!(
+                "Local variable '{name}' cannot have the name of the item defined at {defined}"
+            ))
+        } else if is_bound {
+            Some(format!("Local variable '{name}' is bound twice"))
+        } else {
+            None
+        };
+        match error {
+            Some(error) => Err(CompilerError::TypeError(Some(location.clone()), error)),
+            None => Ok(()),
+        }
+    }
+
+    fn unknown_word(&self, word: &str, location: &Location) -> CompilerError {
+        let mut error: String = format!("Unknown word '{word}'");
+        if let Some(suggestion) = self.closest_word(word) {
+            error.push_str(&format!(", did you mean '{suggestion}'?"));
+        }
+        CompilerError::TypeError(Some(location.clone()), error)
+    }
+
+    /// Local variable, intrinsic, function, struct or memory with the most similar name to the unknown word
+    fn closest_word(&self, word: &str) -> Option<String> {
+        let locals = self.locals.iter().map(|(name, _)| name.clone());
+        let intrinsics = Intrinsic::iter().map(|intrinsic| format!("{intrinsic:?}").to_lowercase());
+        let functions = self
+            .program
+            .functions
+            .iter()
+            .map(|function| function.name.clone());
+        let structs = self
+            .program
+            .structs
+            .iter()
+            .map(|structure| structure.name.clone());
+        let memories = self
+            .program
+            .memories
+            .iter()
+            .map(|memory| memory.name.clone());
+        closest_name(
+            word,
+            locals
+                .chain(intrinsics)
+                .chain(functions)
+                .chain(structs)
+                .chain(memories),
+        )
+    }
+}
+
+/// Name with the smallest edit distance to `word`.
+/// Names which differ too much are not suggested.
+fn closest_name(word: &str, names: impl Iterator<Item = String>) -> Option<String> {
+    let max_distance: usize = (word.chars().count() / 3).max(1);
+    names
+        .map(|name| (edit_distance(word, &name), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// Levenshtein distance between the strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal: usize = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution: usize = previous_diagonal + usize::from(a_char != *b_char);
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(distances[j + 1] + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+pub fn check_intrinsic(
+    intrinsic: Intrinsic,
+    stack: &mut Vec<DataType>,
+    word: &str,
+    location: &Location,
+) -> Result<(), CompilerError> {
+    match intrinsic {
+        Intrinsic::Assert => {
+            let a: Vec<DataType> = pop_arguments(stack, 1, word, location)?;
+            if a[0] != DataType::Boolean {
+                return Err(CompilerError::TypeError(
+                    Some(location.clone()),
+                    format!("Expected bool for '{word}' but got {}", a[0]),
+                ));
+            }
+        }
+        Intrinsic::Drop => {
+            pop_arguments(stack, 1, word, location)?;
+        }
+        Intrinsic::Print => {
+            let a: Vec<DataType> = pop_arguments(stack, 1, word, location)?;
+            match &a[0] {
+                DataType::Custom(name) => {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!("Struct '{name}' cannot be printed, print its fields instead"),
+                    ))
+                }
+                DataType::Array(..) => {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!(
+                            "Array {} cannot be printed, print its elements instead",
+                            a[0]
+                        ),
+                    ))
+                }
+                DataType::Pointer(_) => {
+                    return Err(CompilerError::TypeError(
+                        Some(location.clone()),
+                        format!("Pointer {} cannot be printed, cast it to int instead", a[0]),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Intrinsic::Load8 | Intrinsic::Load16 | Intrinsic::Load32 | Intrinsic::Load64 => {
+            let a: Vec<DataType> = pop_arguments(stack, 1, word, location)?;
+            check_pointer(&a[0], word, location)?;
+            stack.push(a[0].loaded_type(intrinsic.access_size().unwrap()));
+        }
+        Intrinsic::Store8 | Intrinsic::Store16 | Intrinsic::Store32 | Intrinsic::Store64 => {
+            let ab: Vec<DataType> = pop_arguments(stack, 2, word, location)?;
+            check_pointer(&ab[1], word, location)?;
+            let expected: DataType = ab[1].loaded_type(intrinsic.access_size().unwrap());
+            if ab[0] != expected {
+                return Err(CompilerError::TypeError(
+                    Some(location.clone()),
+                    format!(
+                        "Expected {expected} to store with '{word}' but got {}",
+                        ab[0]
+                    ),
+                ));
+            }
+        }
+        Intrinsic::Syscall0
+        | Intrinsic::Syscall1
+        | Intrinsic::Syscall2
+        | Intrinsic::Syscall3
+        | Intrinsic::Syscall4
+        | Intrinsic::Syscall5
+        | Intrinsic::Syscall6 => {
+            let count: usize = intrinsic.syscall_arguments().unwrap();
+            let mut arguments: Vec<DataType> = pop_arguments(stack, count + 1, word, location)?;
+            let number: DataType = arguments.pop().unwrap();
+            if number != DataType::Integer {
+                return Err(CompilerError::TypeError(
+                    Some(location.clone()),
+                    format!("Syscall number of '{word}' should be int but got {number}"),
+                ));
+            }
+            // Arguments are passed in registers so they should fit in one
+            if let Some(typ) = arguments
+                .iter()
+                .find(|typ| typ.integer_type().is_none() && !matches!(typ, DataType::Pointer(_)))
+            {
+                return Err(CompilerError::TypeError(
+                    Some(location.clone()),
+                    format!("Arguments of '{word}' should be integers or ptr but got {typ}"),
+                ));
+            }
+            stack.push(DataType::Integer);
+        }
+        Intrinsic::DivMod => {
+            let ab: Vec<DataType> = pop_arguments(stack, 2, word, location)?;
+            let Some(typ) = common_integer_type(&ab[0], &ab[1]) else {
+                return Err(CompilerError::TypeError(
+                    Some(location.clone()),
+                    format!("Invalid argument types for '{word}': {}", format_types(&ab)),
+                ));
+            };
+            stack.extend([DataType::of_integer(typ), DataType::of_integer(typ)]);
+        }
+        Intrinsic::Dup => {
+            let a: Vec<DataType> = pop_arguments(stack, 1, word, location)?;
+            stack.extend([a[0].clone(), a[0].clone()]);
+        }
+        Intrinsic::Over => {
+            let ab: Vec<DataType> = pop_arguments(stack, 2, word, location)?;
+            stack.extend([ab[0].clone(), ab[1].clone(), ab[0].clone()]);
+        }
+        Intrinsic::Swap => {
+            let ab: Vec<DataType> = pop_arguments(stack, 2, word, location)?;
+            stack.extend([ab[1].clone(), ab[0].clone()]);
+        }
+    }
+    Ok(())
+}
+
+fn check_pointer(typ: &DataType, word: &str, location: &Location) -> Result<(), CompilerError> {
+    match typ {
+        DataType::Pointer(_) => Ok(()),
+        typ => Err(CompilerError::TypeError(
+            Some(location.clone()),
+            format!("Expected a pointer for '{word}' but got {typ}"),
+        )),
+    }
+}
+
+/// Pointers can be made from integers, other pointers and arrays which are
+/// converted to the address of their first element. Pointers can be turned back
+/// into integers. Integers can be cast to each other which keeps their lowest bits.
+fn is_valid_cast(from: &DataType, to: &DataType) -> bool {
+    (from.integer_type().is_some() && to.integer_type().is_some())
+        || matches!(
+            (from, to),
+            (
+                DataType::Integer | DataType::String | DataType::Pointer(_) | DataType::Array(..),
+                DataType::Pointer(_)
+            ) | (DataType::Pointer(_), DataType::Integer)
+        )
+        || from == to
+}
+
+/// Pop `count` values from the stack in the order they were pushed
+fn pop_arguments(
+    stack: &mut Vec<DataType>,
+    count: usize,
+    word: &str,
+    location: &Location,
+) -> Result<Vec<DataType>, CompilerError> {
+    if stack.len() < count {
+        return Err(CompilerError::TypeError(
+            Some(location.clone()),
+            format!(
+                "Not enough values on the stack for '{word}': expected {count} but got {}",
+                stack.len()
+            ),
+        ));
+    }
+    Ok(stack.split_off(stack.len() - count))
+}
+
+/// Pop the arguments of `word` and make sure that they have the expected types
+fn check_arguments(
+    stack: &mut Vec<DataType>,
+    expected: &[DataType],
+    word: &str,
+    location: &Location,
+) -> Result<(), CompilerError> {
+    let arguments: Vec<DataType> = pop_arguments(stack, expected.len(), word, location)?;
+    if arguments != expected {
+        return Err(CompilerError::TypeError(
+            Some(location.clone()),
+            format!(
+                "Invalid arguments for '{word}': expected {} but got {}",
+                format_types(expected),
+                format_types(&arguments)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Integer type of the operands of different integer types, which are converted to it.
+/// Only `int` is converted implicitly, to the type of the other operand, e.g. `x 1 +` for `x: u8`.
+pub fn common_integer_type(a: &DataType, b: &DataType) -> Option<IntegerType> {
+    match (a.integer_type()?, b.integer_type()?) {
+        (a, b) if a == b => Some(a),
+        (IntegerType::I64, typ) | (typ, IntegerType::I64) => Some(typ),
+        _ => None,
+    }
+}
+
+/// Integer type the binary operation is done in or `None` if it does not operate on integers.
+/// Shifts keep the type of the shifted value and take any integer as the amount.
+/// An `int` is compared with another integer type as `int` which holds the values of both,
+/// so `255u8 0 1 - ==` is false. Values of `u64` do not fit in `int` and need a cast.
+pub fn binary_operand_type(
+    operator: &BinaryOperator,
+    a: &DataType,
+    b: &DataType,
+) -> Option<IntegerType> {
+    match operator {
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => {
+            b.integer_type().and(a.integer_type())
+        }
+        BinaryOperator::Assignment | BinaryOperator::And | BinaryOperator::Or => None,
+        BinaryOperator::Equals
+        | BinaryOperator::NotEquals
+        | BinaryOperator::GreaterOrEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::LessOrEqual
+        | BinaryOperator::LessThan => match (a.integer_type()?, b.integer_type()?) {
+            (a, b) if a == b => Some(a),
+            (IntegerType::U64, _) | (_, IntegerType::U64) => None,
+            (IntegerType::I64, _) | (_, IntegerType::I64) => Some(IntegerType::I64),
+            _ => None,
+        },
+        _ => common_integer_type(a, b),
+    }
+}
+
+/// Result type of the binary operation or `None` if the argument types are invalid
+pub fn binary_operation_type(
+    operator: &BinaryOperator,
+    a: &DataType,
+    b: &DataType,
+) -> Option<DataType> {
+    if let Some(typ) = binary_operand_type(operator, a, b) {
+        return match operator {
+            BinaryOperator::Equals
+            | BinaryOperator::NotEquals
+            | BinaryOperator::GreaterOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::LessThan => Some(DataType::Boolean),
+            _ => Some(DataType::of_integer(typ)),
+        };
+    }
+    match (operator, a, b) {
+        (BinaryOperator::And | BinaryOperator::Or, DataType::Boolean, DataType::Boolean) => {
+            Some(DataType::Boolean)
+        }
+        (
+            BinaryOperator::GreaterOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::LessThan,
+            DataType::Character,
+            DataType::Character,
+        ) => Some(DataType::Boolean),
+        // Pointer arithmetic is done in bytes and keeps the type of the pointer
+        (
+            BinaryOperator::Addition | BinaryOperator::Subtraction,
+            DataType::Pointer(_),
+            DataType::Integer,
+        ) => Some(a.clone()),
+        (BinaryOperator::Addition, DataType::Integer, DataType::Pointer(_)) => Some(b.clone()),
+        (BinaryOperator::Subtraction, DataType::Pointer(_), DataType::Pointer(_)) if a == b => {
+            Some(DataType::Integer)
+        }
+        (
+            BinaryOperator::GreaterOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::LessThan,
+            DataType::Pointer(_),
+            DataType::Pointer(_),
+        ) if a == b => Some(DataType::Boolean),
+        // Structs are not compared as they are references in the native code
+        (BinaryOperator::Equals | BinaryOperator::NotEquals, DataType::Custom(_), _) => None,
+        (BinaryOperator::Equals | BinaryOperator::NotEquals, a, b) if a == b => {
+            Some(DataType::Boolean)
+        }
+        _ => None,
+    }
+}
+
+/// Result type of the unary operation or `None` if the argument type is invalid
+pub fn unary_operation_type(operator: &UnaryOperator, a: &DataType) -> Option<DataType> {
+    match (operator, a) {
+        (UnaryOperator::Not, DataType::Boolean) => Some(DataType::Boolean),
+        (UnaryOperator::BitwiseNot, a) if a.integer_type().is_some() => Some(a.clone()),
+        _ => None,
+    }
+}
+
+pub fn format_types(types: &[DataType]) -> String {
+    if types.is_empty() {
+        return "nothing".to_string();
+    }
+    types
+        .iter()
+        .map(|typ| typ.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::TEST_FOLDER, Compiler};
+
+    fn type_check_test_file(file: &str) -> Vec<String> {
+        let mut compiler = Compiler::new();
+        compiler.add_file(&format!("{TEST_FOLDER}/{file}")).unwrap();
+        type_check_functions(&compiler.parse().unwrap())
+            .iter()
+            .map(|error| error.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn type_check_valid_programs() {
+        assert!(type_check_test_file("vm_arithmetic.rot").is_empty());
+        assert!(type_check_test_file("vm_control_flow.rot").is_empty());
+    }
+
+    #[test]
+    fn type_check_errors() {
+        let file: String = format!("{TEST_FOLDER}/type_check_errors.rot");
+        assert_eq!(
+            type_check_test_file("type_check_errors.rot"),
+            vec![
+                format!("Type error: {file}:2:12: Invalid argument types for '+': int bool"),
+                format!("Type error: {file}:6:5: Not enough values on the stack for 'drop': expected 1 but got 0"),
+                format!("Type error: {file}:10:8: Condition of 'if' should leave bool on the stack but got int"),
+                format!("Type error: {file}:14:5: Branches of 'if' leave different types on the stack: int and nothing"),
+                format!("Type error: {file}:21:5: Loop should not change the types on the stack: expected nothing but got int"),
+                format!("Type error: {file}:27:5: Expected 'returns_int' to return int but the stack has str"),
+                format!("Type error: {file}:31:5: Unknown word 'returns_itn', did you mean 'returns_int'?"),
+            ]
+        );
+
+        // Function with an empty body is reported at its name
+        let errors = Compiler::new()
+            .add_source("main.rot", "fun main() {}\nfun f() -> int {}")
+            .check();
+        assert_eq!(
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>(),
+            vec!["Type error: main.rot:2:5: Expected 'f' to return int but the stack has nothing"]
+        );
+    }
+
+    #[test]
+    fn type_check_main_signature() {
+        let file: String = format!("{TEST_FOLDER}/type_check_main_signature.rot");
+        assert_eq!(
+            type_check_test_file("type_check_main_signature.rot"),
+            vec![format!("Type error: {file}:1:5: Function 'main' should take no parameters or 'argc: int, argv: ptr' and return nothing or int but has the signature int -> int")]
+        );
+        let errors = Compiler::new()
+            .add_source(
+                "main.rot",
+                "fun main(argc: int, argv: ptr<int>) -> int { argv drop argc }",
+            )
+            .check();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn type_check_structs() {
+        assert!(type_check_test_file("structs.rot").is_empty());
+        let file: String = format!("{TEST_FOLDER}/type_check_structs.rot");
+        assert_eq!(
+            type_check_test_file("type_check_structs.rot"),
+            vec![
+                format!("Type error: {file}:6:8: Unknown type 'Pointt', did you mean 'Point'?"),
+                format!("Type error: {file}:12:16: Struct 'Point' has no field 'z'"),
+                format!("Type error: {file}:16:8: Expected a struct for the field 'x' but got int"),
+                format!("Type error: {file}:20:12: Invalid arguments for 'Point': expected int int but got int bool"),
+                format!("Type error: {file}:24:15: Struct 'Point' cannot be printed, print its fields instead"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_check_arrays() {
+        assert!(type_check_test_file("arrays.rot").is_empty());
+        let file: String = format!("{TEST_FOLDER}/type_check_arrays.rot");
+        assert_eq!(
+            type_check_test_file("type_check_arrays.rot"),
+            vec![
+                format!("Type error: {file}:2:8: Memory 'count' should be an array but has the type int"),
+                format!("Type error: {file}:3:8: Arrays can only hold bool, char or integers but got str"),
+                format!("Type error: {file}:6:12: Index 4 is out of bounds for int[4]"),
+                format!("Type error: {file}:10:11: Index should be int but got char"),
+                format!("Type error: {file}:14:7: Expected an array to index but got int"),
+                format!("Type error: {file}:18:20: Cannot assign bool to an element of type int"),
+                format!("Type error: {file}:22:12: Array int[4] cannot be printed, print its elements instead"),
+                format!("Type error: {file}:26:9: Only elements of arrays and local variables can be assigned, e.g. 'values[0] ='"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_check_pointers() {
+        assert!(type_check_test_file("pointers.rot").is_empty());
+        let file: String = format!("{TEST_FOLDER}/type_check_pointers.rot");
+        assert_eq!(
+            type_check_test_file("type_check_pointers.rot"),
+            vec![
+                format!("Type error: {file}:4:7: Expected a pointer for 'load8' but got int"),
+                format!("Type error: {file}:8:32: Expected int to store with 'store64' but got bool"),
+                format!("Type error: {file}:12:10: Cannot cast bool to ptr"),
+                format!("Type error: {file}:16:39: Invalid argument types for '+': ptr ptr"),
+                format!("Type error: {file}:20:27: Pointer ptr<int> cannot be printed, cast it to int instead"),
+                format!("Type error: {file}:23:5: Unknown type 'Pointt'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_check_integers() {
+        assert!(type_check_test_file("integers.rot").is_empty());
+        let file: String = format!("{TEST_FOLDER}/type_check_integers.rot");
+        assert_eq!(
+            type_check_test_file("type_check_integers.rot"),
+            vec![
+                format!("Type error: {file}:4:5: Integer literal '300u8' is out of range for u8"),
+                format!("Type error: {file}:8:13: Invalid argument types for '+': u8 i8"),
+                format!("Type error: {file}:13:13: Cannot assign int to the local variable 'x' of type u8"),
+                format!("Type error: {file}:18:10: Cannot cast bool to u8"),
+                format!("Type error: {file}:22:11: Index should be int but got u8"),
+                format!("Type error: {file}:26:9: Invalid argument type for 'not': u8"),
+                format!("Type error: {file}:30:5: Integer literal '9223372036854775808' is out of range for int"),
+                format!("Type error: {file}:34:12: Invalid argument types for '==': u64 int"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_check_locals() {
+        assert!(type_check_test_file("locals.rot").is_empty());
+        let file: String = format!("{TEST_FOLDER}/type_check_locals.rot");
+        assert_eq!(
+            type_check_test_file("type_check_locals.rot"),
+            vec![
+                format!("Type error: {file}:2:5: Not enough values on the stack for 'let': expected 1 but got 0"),
+                format!("Type error: {file}:9:16: Cannot assign bool to the local variable 'x' of type int"),
+                format!("Type error: {file}:14:7: Cannot assign to 'main' which is not a local variable"),
+                format!("Type error: {file}:20:5: Unknown word 'x'"),
+                format!("Type error: {file}:24:15: Local variable 'x' is bound twice"),
+                format!("Type error: {file}:29:11: Local variable 'dup' cannot have the name of an intrinsic"),
+                format!("Type error: {file}:34:11: Local variable 'main' cannot have the name of the item defined at {file}:1:5"),
+                format!("Type error: {file}:40:9: Unknown word 'valu', did you mean 'value'?"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_check_parameters() {
+        let file: String = format!("{TEST_FOLDER}/type_check_parameters.rot");
+        let diagnostics: Vec<String> = Compiler::new()
+            .add_file(&file)
+            .unwrap()
+            .check()
+            .iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                format!("Type error: {file}:11:23: Local variable 'value' is bound twice"),
+                format!("Type error: {file}:15:15: Local variable 'dup' cannot have the name of an intrinsic"),
+                format!("Type error: {file}:18:5: Unknown word 'cont', did you mean 'count'?"),
+                format!("Type error: {file}:22:5: Unknown word 'value'"),
+                format!("Warning: {file}:1:23: Parameter 'factor' of 'scale' is never used"),
+                format!("Warning: {file}:5:14: Parameter 'value' of 'shadowed' is never used"),
+                format!("Warning: {file}:11:11: Parameter 'value' of 'twice' is never used"),
+                format!("Warning: {file}:15:15: Parameter 'dup' of 'intrinsic' is never used"),
+                format!("Warning: {file}:17:16: Parameter 'count' of 'misspelled' is never used"),
+            ]
+        );
+        // Parameters used in nested scopes and assignments are used
+        let program: Program = Compiler::new()
+            .add_file(&format!("{TEST_FOLDER}/locals.rot"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(find_warnings(&program).is_empty());
+    }
+
+    #[test]
+    fn type_check_calls() {
+        assert!(type_check_test_file("calls.rot").is_empty());
+        let file: String = format!("{TEST_FOLDER}/type_check_calls.rot");
+        assert_eq!(
+            type_check_test_file("type_check_calls.rot"),
+            vec![
+                format!("Type error: {file}:6:5: Wrong number of arguments for 'add': expected 2 but got 1"),
+                format!("Type error: {file}:10:12: Argument 2 of 'add' should push int but pushes bool"),
+                format!("Type error: {file}:14:9: Argument 1 of 'add' should push int but pushes int int"),
+                format!("Type error: {file}:18:11: Not enough values on the stack for '+': expected 2 but got 0"),
+                format!("Type error: {file}:22:5: Only functions can be called with arguments but 'print' is not a function"),
+                format!("Type error: {file}:26:5: Unknown word 'ad', did you mean 'add'?"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_check_syscalls() {
+        assert!(type_check_test_file("syscalls.rot").is_empty());
+        let check = |code: &str| {
+            let program: Program = Compiler::new()
+                .add_source("main.rot", code)
+                .parse()
+                .unwrap();
+            type_check_functions(&program)[0].to_string()
+        };
+        assert_eq!(
+            check("fun main() { 1 \"60\" syscall1 drop }"),
+            "Type error: main.rot:1:21: Syscall number of 'syscall1' should be int but got str"
+        );
+        assert_eq!(
+            check("fun main() { true 60 syscall1 drop }"),
+            "Type error: main.rot:1:22: Arguments of 'syscall1' should be integers or ptr but got bool"
+        );
+    }
+
+    #[test]
+    fn type_check_operators() {
+        assert!(type_check_test_file("operators.rot").is_empty());
+        let check = |code: &str| {
+            let program: Program = Compiler::new()
+                .add_source("main.rot", code)
+                .parse()
+                .unwrap();
+            type_check_functions(&program)[0].to_string()
+        };
+        assert_eq!(
+            check("fun main() { true 1 and drop }"),
+            "Type error: main.rot:1:21: Invalid argument types for 'and': bool int"
+        );
+        assert_eq!(
+            check("fun main() { true false | drop }"),
+            "Type error: main.rot:1:25: Invalid argument types for '|': bool bool"
+        );
+        assert_eq!(
+            check("fun main() { 1 not drop }"),
+            "Type error: main.rot:1:16: Invalid argument type for 'not': int"
+        );
+        assert_eq!(
+            check("fun main() { true ~ drop }"),
+            "Type error: main.rot:1:19: Invalid argument type for '~': bool"
+        );
+        assert_eq!(
+            check("fun main() { 'a' 2 divmod drop drop }"),
+            "Type error: main.rot:1:20: Invalid argument types for 'divmod': char int"
+        );
+    }
+
+    #[test]
+    fn closest_word_suggestion() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "dup"), 3);
+        let program = Program::default();
+        let checker = TypeChecker {
+            program: &program,
+            name: "main",
+            return_type: &[],
+            loops: Vec::new(),
+            locals: Vec::new(),
+        };
+        assert_eq!(checker.closest_word("dupp"), Some("dup".to_string()));
+        assert_eq!(checker.closest_word("prnt"), Some("print".to_string()));
+        assert_eq!(checker.closest_word("unrelated"), None);
+    }
+}
@@ -0,0 +1,917 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::ops::Range;
+
+use crate::bytecode::{Bytecode, Instruction};
+use crate::class::intrinsic::Intrinsic;
+use crate::class::token::{BinaryOperator, UnaryOperator};
+use crate::compiler::CompilerError;
+use crate::data_types::{DataType, IntegerType};
+
+/// Maximum depth of nested function calls before the VM reports a stack overflow
+const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Linux syscalls emulated by the VM
+const SYS_READ: i64 = 0;
+const SYS_WRITE: i64 = 1;
+const SYS_EXIT: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Boolean(bool),
+    Character(char),
+    Integer(i64),
+    String(String),
+    /// Name of the struct and its fields with their names
+    Struct(String, Vec<(String, Value)>),
+    /// Type of the array and the address of its first element in the memory
+    Array(DataType, usize),
+    /// Type of the pointer and the address in the memory
+    Pointer(DataType, usize),
+    /// Integer other than `int` sign or zero extended to 64 bits
+    SizedInteger(IntegerType, i64),
+}
+
+impl Value {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Value::Boolean(_) => DataType::Boolean,
+            Value::Character(_) => DataType::Character,
+            Value::Integer(_) => DataType::Integer,
+            Value::String(_) => DataType::String,
+            Value::Struct(name, _) => DataType::Custom(name.clone()),
+            Value::Array(typ, _) | Value::Pointer(typ, _) => typ.clone(),
+            Value::SizedInteger(typ, _) => DataType::SizedInteger(*typ),
+        }
+    }
+
+    /// Integer of the type which is already sign or zero extended
+    pub fn integer(typ: IntegerType, value: i64) -> Value {
+        match typ {
+            IntegerType::I64 => Value::Integer(value),
+            typ => Value::SizedInteger(typ, value),
+        }
+    }
+
+    /// Type and value of integers
+    fn as_integer(&self) -> Option<(IntegerType, i64)> {
+        match self {
+            Value::Integer(value) => Some((IntegerType::I64, *value)),
+            Value::SizedInteger(typ, value) => Some((*typ, *value)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Boolean(boolean) => write!(f, "{boolean}"),
+            Value::Character(character) => write!(f, "{character}"),
+            Value::Integer(integer) => write!(f, "{integer}"),
+            Value::String(string) => write!(f, "{string}"),
+            Value::Struct(name, fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {value}"))
+                    .collect();
+                write!(f, "{name} {{ {} }}", fields.join(", "))
+            }
+            Value::Array(_, address) | Value::Pointer(_, address) => write!(f, "{address:#x}"),
+            Value::SizedInteger(IntegerType::U64, value) => write!(f, "{}", *value as u64),
+            Value::SizedInteger(_, value) => write!(f, "{value}"),
+        }
+    }
+}
+
+struct Frame {
+    function: usize,
+    /// Index of the next instruction to execute
+    ip: usize,
+    locals: Vec<Value>,
+}
+
+impl Frame {
+    fn new(bytecode: &Bytecode, function: usize) -> Self {
+        Frame {
+            function,
+            ip: 0,
+            // Variables are always assigned before they are read
+            locals: vec![Value::Integer(0); bytecode.functions[function].locals],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Vm {
+    pub stack: Vec<Value>,
+    /// Memories of the program which hold the elements of the arrays
+    pub memory: Vec<u8>,
+    /// Exit code of the `exit` syscall which stops the execution
+    pub exit_code: Option<i32>,
+    /// Addresses of the strings cast to pointers, copied after the memories
+    strings: HashMap<String, usize>,
+}
+
+/// Execute the entry function of the bytecode and return the final stack.
+/// The command-line arguments starting with the program name are passed to
+/// the entry function if it takes them and its returned value is the exit code.
+/// A non-zero exit code is returned as an error.
+pub fn run_bytecode(
+    bytecode: &Bytecode,
+    args: &[String],
+    output: &mut dyn Write,
+) -> Result<Vec<Value>, CompilerError> {
+    match run_bytecode_with_exit_code(bytecode, args, output)? {
+        (_, code) if code != 0 => Err(CompilerError::RuntimeError(
+            None,
+            format!("Program exited with exit status: {code}"),
+        )),
+        (stack, _) => Ok(stack),
+    }
+}
+
+/// Execute the entry function of the bytecode like `run_bytecode` and return
+/// the final stack with the exit code of the program, zero if it sets none.
+pub fn run_bytecode_with_exit_code(
+    bytecode: &Bytecode,
+    args: &[String],
+    output: &mut dyn Write,
+) -> Result<(Vec<Value>, i32), CompilerError> {
+    let mut vm = Vm::default();
+    let entry = &bytecode.functions[bytecode.entry];
+    if entry.parameters > 0 {
+        vm.memory.resize(bytecode.memory_size, 0);
+        let argv: usize = vm.arguments_address(args);
+        vm.stack.push(Value::Integer(args.len() as i64));
+        vm.stack.push(Value::Pointer(DataType::Pointer(None), argv));
+    }
+    vm.execute(bytecode, bytecode.entry, output)?;
+    if entry.returns > 0 && vm.exit_code.is_none() {
+        match vm.stack.pop() {
+            Some(Value::Integer(code)) => vm.exit_code = Some(code as i32),
+            _ => {
+                return Err(CompilerError::RuntimeError(
+                    None,
+                    "Expected an exit code".to_string(),
+                ))
+            }
+        }
+    }
+    Ok((vm.stack, vm.exit_code.unwrap_or(0)))
+}
+
+impl Vm {
+    /// Call `function` and run until it returns. The stack is preserved between executions.
+    pub fn execute(
+        &mut self,
+        bytecode: &Bytecode,
+        function: usize,
+        output: &mut dyn Write,
+    ) -> Result<(), CompilerError> {
+        if self.memory.len() < bytecode.memory_size {
+            self.memory.resize(bytecode.memory_size, 0);
+        }
+        self.exit_code = None;
+        let mut frames: Vec<Frame> = vec![Frame::new(bytecode, function)];
+        while let Some(frame) = frames.last_mut() {
+            let instruction: &Instruction =
+                &bytecode.functions[frame.function].instructions[frame.ip];
+            frame.ip += 1;
+            if let Err(message) = self.step(bytecode, instruction, &mut frames, output) {
+                return Err(runtime_error(bytecode, &frames, &message));
+            }
+            if self.exit_code.is_some() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn step(
+        &mut self,
+        bytecode: &Bytecode,
+        instruction: &Instruction,
+        frames: &mut Vec<Frame>,
+        output: &mut dyn Write,
+    ) -> Result<(), String> {
+        match instruction {
+            Instruction::Push(index) => self.stack.push(bytecode.constants[*index].clone()),
+            Instruction::Call(function) => {
+                if frames.len() >= MAX_CALL_DEPTH {
+                    return Err("Stack overflow".to_string());
+                }
+                frames.push(Frame::new(bytecode, *function));
+            }
+            Instruction::Intrinsic(intrinsic) => self.intrinsic(*intrinsic, output)?,
+            Instruction::Binary(operator) => {
+                let b: Value = self.pop()?;
+                let a: Value = self.pop()?;
+                self.stack.push(binary_operation(operator, a, b)?);
+            }
+            Instruction::Unary(operator) => {
+                let a: Value = self.pop()?;
+                self.stack.push(unary_operation(operator, a)?);
+            }
+            Instruction::Construct(index) => {
+                let structure = &bytecode.structs[*index];
+                if self.stack.len() < structure.fields.len() {
+                    return Err("Stack underflow".to_string());
+                }
+                let values: Vec<Value> = self
+                    .stack
+                    .split_off(self.stack.len() - structure.fields.len());
+                let fields: Vec<(String, Value)> =
+                    structure.fields.iter().cloned().zip(values).collect();
+                self.stack
+                    .push(Value::Struct(structure.name.clone(), fields));
+            }
+            Instruction::Field(name) => {
+                let value: Value = match self.pop()? {
+                    Value::Struct(_, fields) => fields.into_iter().find(|(field, _)| field == name),
+                    _ => None,
+                }
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("Value has no field '{name}'"))?;
+                self.stack.push(value);
+            }
+            Instruction::Load => {
+                let (element, address) = self.pop_element_address()?;
+                let value: Value = self.load(&element, address, element.size())?;
+                self.stack.push(value);
+            }
+            Instruction::Store => {
+                let (element, address) = self.pop_element_address()?;
+                let value: Value = self.pop()?;
+                self.store(address, element.size(), value)?;
+            }
+            Instruction::Cast(typ) => {
+                let value: Value = match (self.pop()?, typ) {
+                    (Value::String(string), DataType::Pointer(_)) => {
+                        Value::Pointer(typ.clone(), self.string_address(string))
+                    }
+                    (value, typ) => cast(value, typ)?,
+                };
+                self.stack.push(value);
+            }
+            Instruction::GetLocal(slot) => {
+                let value: Value = frames.last().unwrap().locals[*slot].clone();
+                self.stack.push(value);
+            }
+            Instruction::SetLocal(slot) => {
+                let value: Value = self.pop()?;
+                frames.last_mut().unwrap().locals[*slot] = value;
+            }
+            Instruction::Jump(address) => frames.last_mut().unwrap().ip = *address,
+            Instruction::JumpIfFalse(address) => match self.pop()? {
+                Value::Boolean(true) => {}
+                Value::Boolean(false) => frames.last_mut().unwrap().ip = *address,
+                value => {
+                    return Err(format!(
+                        "Expected a condition of type bool but got {}",
+                        value.data_type()
+                    ))
+                }
+            },
+            Instruction::Return => {
+                frames.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn intrinsic(&mut self, intrinsic: Intrinsic, output: &mut dyn Write) -> Result<(), String> {
+        match intrinsic {
+            Intrinsic::Assert => match self.pop()? {
+                Value::Boolean(true) => {}
+                Value::Boolean(false) => return Err("Assertion failed".to_string()),
+                value => {
+                    return Err(format!(
+                        "Expected bool for assert but got {}",
+                        value.data_type()
+                    ))
+                }
+            },
+            Intrinsic::Drop => {
+                self.pop()?;
+            }
+            Intrinsic::Dup => {
+                let a: Value = self.pop()?;
+                self.stack.push(a.clone());
+                self.stack.push(a);
+            }
+            Intrinsic::Over => {
+                let b: Value = self.pop()?;
+                let a: Value = self.pop()?;
+                self.stack.push(a.clone());
+                self.stack.push(b);
+                self.stack.push(a);
+            }
+            Intrinsic::Print => {
+                let a: Value = self.pop()?;
+                writeln!(output, "{a}").map_err(|error| error.to_string())?;
+            }
+            Intrinsic::Swap => {
+                let b: Value = self.pop()?;
+                let a: Value = self.pop()?;
+                self.stack.push(b);
+                self.stack.push(a);
+            }
+            Intrinsic::DivMod => {
+                let b: Value = self.pop()?;
+                let a: Value = self.pop()?;
+                let quotient: Value =
+                    binary_operation(&BinaryOperator::Division, a.clone(), b.clone())?;
+                let remainder: Value = binary_operation(&BinaryOperator::Modulo, a, b)?;
+                self.stack.push(quotient);
+                self.stack.push(remainder);
+            }
+            Intrinsic::Load8 | Intrinsic::Load16 | Intrinsic::Load32 | Intrinsic::Load64 => {
+                let size: usize = intrinsic.access_size().unwrap();
+                let (typ, address) = self.pop_pointer(intrinsic)?;
+                let value: Value = self.load(&typ.loaded_type(size), address, size)?;
+                self.stack.push(value);
+            }
+            Intrinsic::Store8 | Intrinsic::Store16 | Intrinsic::Store32 | Intrinsic::Store64 => {
+                let (_, address) = self.pop_pointer(intrinsic)?;
+                let value: Value = self.pop()?;
+                self.store(address, intrinsic.access_size().unwrap(), value)?;
+            }
+            Intrinsic::Syscall0
+            | Intrinsic::Syscall1
+            | Intrinsic::Syscall2
+            | Intrinsic::Syscall3
+            | Intrinsic::Syscall4
+            | Intrinsic::Syscall5
+            | Intrinsic::Syscall6 => {
+                let number: Value = self.pop()?;
+                let count: usize = intrinsic.syscall_arguments().unwrap();
+                if self.stack.len() < count {
+                    return Err("Stack underflow".to_string());
+                }
+                let mut arguments: Vec<i64> = Vec::new();
+                for value in self
+                    .stack
+                    .split_off(self.stack.len() - count)
+                    .into_iter()
+                    .chain([number])
+                {
+                    arguments.push(match value {
+                        Value::Integer(integer) | Value::SizedInteger(_, integer) => integer,
+                        Value::Pointer(_, address) => address as i64,
+                        value => {
+                            return Err(format!(
+                                "Expected an integer or ptr for {intrinsic:?} but got {}",
+                                value.data_type()
+                            ))
+                        }
+                    });
+                }
+                let number: i64 = arguments.pop().unwrap();
+                let result: i64 = self.syscall(number, &arguments, output)?;
+                self.stack.push(Value::Integer(result));
+            }
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "Stack underflow".to_string())
+    }
+
+    /// Pop the array and the index and return the type and the address of the element
+    fn pop_element_address(&mut self) -> Result<(DataType, usize), String> {
+        let index: Value = self.pop()?;
+        let array: Value = self.pop()?;
+        let (Value::Array(DataType::Array(element, length), address), Value::Integer(index)) =
+            (&array, &index)
+        else {
+            return Err(format!(
+                "Cannot index {} with {}",
+                array.data_type(),
+                index.data_type()
+            ));
+        };
+        if *index < 0 || *index as usize >= *length {
+            return Err(format!(
+                "Index {index} is out of bounds for {}",
+                array.data_type()
+            ));
+        }
+        Ok((*element.clone(), address + *index as usize * element.size()))
+    }
+
+    /// Emulate the syscalls for the standard streams and exiting the program
+    fn syscall(
+        &mut self,
+        number: i64,
+        arguments: &[i64],
+        output: &mut dyn Write,
+    ) -> Result<i64, String> {
+        match (number, arguments) {
+            (SYS_READ, [0, address, length]) => {
+                let range: Range<usize> = self.memory_range(*address, *length)?;
+                std::io::stdin()
+                    .read(&mut self.memory[range])
+                    .map(|count| count as i64)
+                    .map_err(|error| error.to_string())
+            }
+            (SYS_WRITE, [descriptor @ (1 | 2), address, length]) => {
+                let bytes: &[u8] = &self.memory[self.memory_range(*address, *length)?];
+                let written = match descriptor {
+                    1 => output.write_all(bytes),
+                    _ => std::io::stderr().write_all(bytes),
+                };
+                written.map_err(|error| error.to_string())?;
+                Ok(*length)
+            }
+            (SYS_EXIT, [code]) => {
+                self.exit_code = Some(*code as i32);
+                Ok(0)
+            }
+            _ => Err(format!(
+                "Syscall {number} with {} arguments is not supported by the virtual machine",
+                arguments.len()
+            )),
+        }
+    }
+
+    fn memory_range(&self, address: i64, length: i64) -> Result<Range<usize>, String> {
+        let (address, length) = (address as usize, length as usize);
+        match address.checked_add(length) {
+            Some(end) if end <= self.memory.len() => Ok(address..end),
+            _ => Err("Memory access out of bounds".to_string()),
+        }
+    }
+
+    /// Address of the string ending with a zero byte. Each string is copied to the memory once.
+    fn string_address(&mut self, string: String) -> usize {
+        if let Some(address) = self.strings.get(&string) {
+            return *address;
+        }
+        let address: usize = self.memory.len();
+        self.memory.extend(string.bytes().chain(std::iter::once(0)));
+        self.strings.insert(string, address);
+        address
+    }
+
+    /// Address of the array of pointers to the arguments ending with a null pointer
+    /// like `argv` on the initial process stack
+    fn arguments_address(&mut self, args: &[String]) -> usize {
+        let addresses: Vec<usize> = args
+            .iter()
+            .map(|arg| self.string_address(arg.clone()))
+            .collect();
+        self.memory.resize(self.memory.len().next_multiple_of(8), 0);
+        let argv: usize = self.memory.len();
+        for address in addresses.into_iter().chain(std::iter::once(0)) {
+            self.memory.extend((address as u64).to_le_bytes());
+        }
+        argv
+    }
+
+    /// Pop the pointer used by the memory intrinsic and return its type and address
+    fn pop_pointer(&mut self, intrinsic: Intrinsic) -> Result<(DataType, usize), String> {
+        match self.pop()? {
+            Value::Pointer(typ, address) => Ok((typ, address)),
+            value => Err(format!(
+                "Expected a pointer for {intrinsic:?} but got {}",
+                value.data_type()
+            )),
+        }
+    }
+
+    /// Load `size` bytes to a value of the type. The bytes are zero extended
+    /// unless the type is a signed integer of the same size.
+    fn load(&self, typ: &DataType, address: usize, size: usize) -> Result<Value, String> {
+        let bytes: &[u8] = address
+            .checked_add(size)
+            .and_then(|end| self.memory.get(address..end))
+            .ok_or_else(|| "Memory access out of bounds".to_string())?;
+        let mut buffer: [u8; 8] = [0; 8];
+        buffer[..size].copy_from_slice(bytes);
+        let bits: u64 = u64::from_le_bytes(buffer);
+        match typ {
+            DataType::Boolean => Ok(Value::Boolean(bits != 0)),
+            DataType::Character => char::from_u32(bits as u32)
+                .map(Value::Character)
+                .ok_or_else(|| "Invalid character in memory".to_string()),
+            DataType::Integer => Ok(Value::Integer(bits as i64)),
+            DataType::SizedInteger(typ) => Ok(Value::integer(*typ, typ.wrap(bits as i64))),
+            DataType::Pointer(_) => Ok(Value::Pointer(typ.clone(), bits as usize)),
+            typ => Err(format!("Cannot load {typ} from memory")),
+        }
+    }
+
+    /// Store the lowest `size` bytes of the value
+    fn store(&mut self, address: usize, size: usize, value: Value) -> Result<(), String> {
+        let bits: u64 = match value {
+            Value::Boolean(boolean) => boolean as u64,
+            Value::Character(character) => character as u64,
+            Value::Integer(integer) | Value::SizedInteger(_, integer) => integer as u64,
+            Value::Pointer(_, address) => address as u64,
+            value => return Err(format!("Cannot store {} in memory", value.data_type())),
+        };
+        address
+            .checked_add(size)
+            .and_then(|end| self.memory.get_mut(address..end))
+            .ok_or_else(|| "Memory access out of bounds".to_string())?
+            .copy_from_slice(&bits.to_le_bytes()[..size]);
+        Ok(())
+    }
+}
+
+fn binary_operation(operator: &BinaryOperator, a: Value, b: Value) -> Result<Value, String> {
+    if let (Some((a_type, a)), Some((b_type, b))) = (a.as_integer(), b.as_integer()) {
+        // In arithmetic an int operand is converted to the type of the other one
+        // and shifts keep the type of the shifted value
+        let typ: IntegerType = match operator {
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => a_type,
+            _ if a_type == IntegerType::I64 => b_type,
+            _ => a_type,
+        };
+        // Integers of different types are compared by their values, e.g. `255u8 0 1 - ==` is false
+        let widen = |typ: IntegerType, value: i64| match typ {
+            IntegerType::U64 => value as u64 as i128,
+            _ => value as i128,
+        };
+        if a_type != b_type {
+            if let Some(result) = compare(operator, widen(a_type, a).cmp(&widen(b_type, b))) {
+                return Ok(Value::Boolean(result));
+            }
+        }
+        if a_type != IntegerType::I64 || b_type != IntegerType::I64 {
+            return integer_operation(operator, typ, a, b);
+        }
+    }
+    let value: Value = match (operator, &a, &b) {
+        (BinaryOperator::Addition, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_add(*b))
+        }
+        (BinaryOperator::Subtraction, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_sub(*b))
+        }
+        (BinaryOperator::Multiplication, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_mul(*b))
+        }
+        (BinaryOperator::Division, Value::Integer(_), Value::Integer(0)) => {
+            return Err("Division by zero".to_string())
+        }
+        (BinaryOperator::Division, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_div(*b))
+        }
+        (BinaryOperator::Modulo, Value::Integer(_), Value::Integer(0)) => {
+            return Err("Division by zero".to_string())
+        }
+        (BinaryOperator::Modulo, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_rem(*b))
+        }
+        (BinaryOperator::BitwiseAnd, Value::Integer(a), Value::Integer(b)) => Value::Integer(a & b),
+        (BinaryOperator::BitwiseOr, Value::Integer(a), Value::Integer(b)) => Value::Integer(a | b),
+        (BinaryOperator::BitwiseXor, Value::Integer(a), Value::Integer(b)) => Value::Integer(a ^ b),
+        // Shift counts wrap at 64 like in the native code
+        (BinaryOperator::ShiftLeft, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_shl(*b as u32))
+        }
+        (BinaryOperator::ShiftRight, Value::Integer(a), Value::Integer(b)) => {
+            Value::Integer(a.wrapping_shr(*b as u32))
+        }
+        (BinaryOperator::And, Value::Boolean(a), Value::Boolean(b)) => Value::Boolean(*a && *b),
+        (BinaryOperator::Or, Value::Boolean(a), Value::Boolean(b)) => Value::Boolean(*a || *b),
+        (BinaryOperator::Addition, Value::Pointer(typ, a), Value::Integer(b))
+        | (BinaryOperator::Addition, Value::Integer(b), Value::Pointer(typ, a)) => {
+            Value::Pointer(typ.clone(), a.wrapping_add(*b as usize))
+        }
+        (BinaryOperator::Subtraction, Value::Pointer(typ, a), Value::Integer(b)) => {
+            Value::Pointer(typ.clone(), a.wrapping_sub(*b as usize))
+        }
+        (BinaryOperator::Subtraction, Value::Pointer(_, a), Value::Pointer(_, b)) => {
+            Value::Integer(a.wrapping_sub(*b) as i64)
+        }
+        (BinaryOperator::Equals, a, b) if a.data_type() == b.data_type() => Value::Boolean(a == b),
+        (BinaryOperator::NotEquals, a, b) if a.data_type() == b.data_type() => {
+            Value::Boolean(a != b)
+        }
+        (BinaryOperator::GreaterOrEqual, Value::Integer(a), Value::Integer(b)) => {
+            Value::Boolean(a >= b)
+        }
+        (BinaryOperator::GreaterThan, Value::Integer(a), Value::Integer(b)) => {
+            Value::Boolean(a > b)
+        }
+        (BinaryOperator::LessOrEqual, Value::Integer(a), Value::Integer(b)) => {
+            Value::Boolean(a <= b)
+        }
+        (BinaryOperator::LessThan, Value::Integer(a), Value::Integer(b)) => Value::Boolean(a < b),
+        (BinaryOperator::GreaterOrEqual, Value::Character(a), Value::Character(b)) => {
+            Value::Boolean(a >= b)
+        }
+        (BinaryOperator::GreaterThan, Value::Character(a), Value::Character(b)) => {
+            Value::Boolean(a > b)
+        }
+        (BinaryOperator::LessOrEqual, Value::Character(a), Value::Character(b)) => {
+            Value::Boolean(a <= b)
+        }
+        (BinaryOperator::LessThan, Value::Character(a), Value::Character(b)) => {
+            Value::Boolean(a < b)
+        }
+        (BinaryOperator::GreaterOrEqual, Value::Pointer(_, a), Value::Pointer(_, b)) => {
+            Value::Boolean(a >= b)
+        }
+        (BinaryOperator::GreaterThan, Value::Pointer(_, a), Value::Pointer(_, b)) => {
+            Value::Boolean(a > b)
+        }
+        (BinaryOperator::LessOrEqual, Value::Pointer(_, a), Value::Pointer(_, b)) => {
+            Value::Boolean(a <= b)
+        }
+        (BinaryOperator::LessThan, Value::Pointer(_, a), Value::Pointer(_, b)) => {
+            Value::Boolean(a < b)
+        }
+        _ => {
+            return Err(format!(
+                "Invalid operand types {} and {} for {operator:?}",
+                a.data_type(),
+                b.data_type()
+            ))
+        }
+    };
+    Ok(value)
+}
+
+/// Operation on the integers of the type. Arithmetic on the checked types fails
+/// when the operands or the result do not fit in the type and others wrap around.
+fn integer_operation(
+    operator: &BinaryOperator,
+    typ: IntegerType,
+    a: i64,
+    b: i64,
+) -> Result<Value, String> {
+    let fit = |value: i64| match typ.is_checked() && !typ.contains(value) {
+        true => Err("Integer overflow".to_string()),
+        false => Ok(typ.wrap(value)),
+    };
+    // Amount of the shift can be of any integer type
+    let (a, b): (i64, i64) = match operator {
+        BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => (a, b),
+        _ => (fit(a)?, fit(b)?),
+    };
+    let integer: i64 = match operator {
+        BinaryOperator::Addition => fit(a.wrapping_add(b))?,
+        BinaryOperator::Subtraction => fit(a.wrapping_sub(b))?,
+        BinaryOperator::Multiplication => fit(a.wrapping_mul(b))?,
+        BinaryOperator::Division | BinaryOperator::Modulo if b == 0 => {
+            return Err("Division by zero".to_string())
+        }
+        BinaryOperator::Division if typ.is_signed() => fit(a.wrapping_div(b))?,
+        BinaryOperator::Division => (a as u64 / b as u64) as i64,
+        BinaryOperator::Modulo if typ.is_signed() => a.wrapping_rem(b),
+        BinaryOperator::Modulo => (a as u64 % b as u64) as i64,
+        BinaryOperator::BitwiseAnd => a & b,
+        BinaryOperator::BitwiseOr => a | b,
+        BinaryOperator::BitwiseXor => a ^ b,
+        // Shifted out bits are dropped even from the checked types
+        BinaryOperator::ShiftLeft => typ.wrap(a.wrapping_shl(b as u32)),
+        BinaryOperator::ShiftRight if typ.is_signed() => a.wrapping_shr(b as u32),
+        BinaryOperator::ShiftRight => (a as u64).wrapping_shr(b as u32) as i64,
+        operator => {
+            let ordering: Ordering = match typ.is_signed() {
+                true => a.cmp(&b),
+                false => (a as u64).cmp(&(b as u64)),
+            };
+            return compare(operator, ordering)
+                .map(Value::Boolean)
+                .ok_or_else(|| format!("Invalid operand type {typ} for {operator:?}"));
+        }
+    };
+    Ok(Value::integer(typ, integer))
+}
+
+/// Result of the comparison of two values with the ordering or `None` for other operators
+fn compare(operator: &BinaryOperator, ordering: Ordering) -> Option<bool> {
+    match operator {
+        BinaryOperator::Equals => Some(ordering.is_eq()),
+        BinaryOperator::NotEquals => Some(ordering.is_ne()),
+        BinaryOperator::GreaterOrEqual => Some(ordering.is_ge()),
+        BinaryOperator::GreaterThan => Some(ordering.is_gt()),
+        BinaryOperator::LessOrEqual => Some(ordering.is_le()),
+        BinaryOperator::LessThan => Some(ordering.is_lt()),
+        _ => None,
+    }
+}
+
+fn unary_operation(operator: &UnaryOperator, a: Value) -> Result<Value, String> {
+    match (operator, a) {
+        (UnaryOperator::Not, Value::Boolean(a)) => Ok(Value::Boolean(!a)),
+        (UnaryOperator::BitwiseNot, Value::Integer(a)) => Ok(Value::Integer(!a)),
+        (UnaryOperator::BitwiseNot, Value::SizedInteger(typ, a)) => {
+            Ok(Value::integer(typ, typ.wrap(!a)))
+        }
+        (operator, a) => Err(format!(
+            "Invalid operand type {} for {operator:?}",
+            a.data_type()
+        )),
+    }
+}
+
+/// Convert the value to the type. Arrays are converted to the address of their first element
+/// and integers converted to other integer types keep their lowest bits.
+fn cast(value: Value, typ: &DataType) -> Result<Value, String> {
+    if let (Some((_, integer)), Some(typ)) = (value.as_integer(), typ.integer_type()) {
+        return Ok(Value::integer(typ, typ.wrap(integer)));
+    }
+    let value: Value = match (value, typ) {
+        (Value::Integer(integer), DataType::Pointer(_)) => {
+            Value::Pointer(typ.clone(), integer as usize)
+        }
+        (Value::Pointer(_, address) | Value::Array(_, address), DataType::Pointer(_)) => {
+            Value::Pointer(typ.clone(), address)
+        }
+        (Value::Pointer(_, address), DataType::Integer) => Value::Integer(address as i64),
+        (value, typ) if value.data_type() == *typ => value,
+        (value, typ) => return Err(format!("Cannot cast {} to {typ}", value.data_type())),
+    };
+    Ok(value)
+}
+
+/// Format the error message with a stack trace pointing to the source locations
+fn runtime_error(bytecode: &Bytecode, frames: &[Frame], message: &str) -> CompilerError {
+    let mut error: String = message.to_string();
+    for frame in frames.iter().rev() {
+        let function = &bytecode.functions[frame.function];
+        // The instruction pointer has already moved past the failing instruction
+        let location = &function.locations[frame.ip.saturating_sub(1)];
+        error.push_str(&format!("\n  at {} ({location})", function.name));
+    }
+    CompilerError::RuntimeError(None, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::TEST_FOLDER, Compiler};
+
+    fn run_test_file(file: &str) -> (Result<Vec<Value>, CompilerError>, String) {
+        let mut compiler = Compiler::new();
+        compiler.add_file(&format!("{TEST_FOLDER}/{file}")).unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        let result = compiler.run(&mut output);
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn vm_arithmetic() {
+        let (result, output) = run_test_file("vm_arithmetic.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(output, "69\n-3\n42\n7\ntrue\nfalse\n");
+    }
+
+    #[test]
+    fn vm_control_flow() {
+        let (result, output) = run_test_file("vm_control_flow.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(output, "0\n1\n2\nsmall\nmedium\nlarge\ndone\n");
+    }
+
+    #[test]
+    fn vm_arrays() {
+        let (result, output) = run_test_file("arrays.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(output, "9\n30\n16\n4\nfalse\ntrue\no\nk\n");
+
+        let (result, _) = run_test_file("array_bounds.rot");
+        match result {
+            Err(CompilerError::RuntimeError(None, message)) => assert_eq!(
+                message,
+                format!("Index 4 is out of bounds for int[4]\n  at main ({TEST_FOLDER}/array_bounds.rot:4:17)")
+            ),
+            _ => panic!("Expected runtime error"),
+        }
+    }
+
+    #[test]
+    fn vm_locals() {
+        let (result, output) = run_test_file("locals.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(
+            output,
+            std::fs::read_to_string(format!("{TEST_FOLDER}/locals.stdout")).unwrap()
+        );
+    }
+
+    #[test]
+    fn vm_pointers() {
+        let (result, output) = run_test_file("pointers.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(
+            output,
+            std::fs::read_to_string(format!("{TEST_FOLDER}/pointers.stdout")).unwrap()
+        );
+    }
+
+    #[test]
+    fn vm_operators() {
+        let (result, output) = run_test_file("operators.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(
+            output,
+            std::fs::read_to_string(format!("{TEST_FOLDER}/operators.stdout")).unwrap()
+        );
+    }
+
+    #[test]
+    fn vm_integers() {
+        let (result, output) = run_test_file("integers.rot");
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(
+            output,
+            std::fs::read_to_string(format!("{TEST_FOLDER}/integers.stdout")).unwrap()
+        );
+    }
+
+    #[test]
+    fn vm_syscalls() {
+        let (result, output) = run_test_file("syscalls.rot");
+        assert_eq!(output, "hi\n3\n");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Runtime error: Program exited with exit status: 3"
+        );
+
+        let mut output: Vec<u8> = Vec::new();
+        let result = Compiler::new()
+            .add_source("main.rot", "fun main() { 0 60 syscall1 print 1 print }")
+            .run(&mut output);
+        assert_eq!(result.unwrap(), vec![Value::Integer(0)]);
+        assert!(output.is_empty());
+        let result = Compiler::new()
+            .add_source("main.rot", "fun main() { 9 syscall0 drop }")
+            .run(&mut output);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Runtime error: Syscall 9 with 0 arguments is not supported by the virtual machine\n  at main (main.rot:1:16)"
+        );
+    }
+
+    #[test]
+    fn vm_standard_library() {
+        let (result, output) = run_test_file("standard_library.rot");
+        assert_eq!(
+            output,
+            std::fs::read_to_string(format!("{TEST_FOLDER}/standard_library.stdout")).unwrap()
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Runtime error: Program exited with exit status: 2"
+        );
+
+        // Strings cast to pointers are copied to the memory once
+        let mut output: Vec<u8> = Vec::new();
+        let result = Compiler::new()
+            .add_source("main.rot", "fun main() { \"ab\" cast(ptr) \"ab\" cast(ptr) == print \"ab\" cast(ptr) 1 + load8 print }")
+            .run(&mut output);
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(String::from_utf8(output).unwrap(), "true\n98\n");
+    }
+
+    #[test]
+    fn vm_main_arguments() {
+        let mut compiler = Compiler::new();
+        compiler
+            .add_file(&format!("{TEST_FOLDER}/main_arguments.rot"))
+            .unwrap();
+        let mut output: Vec<u8> = Vec::new();
+        let result = compiler
+            .args(&["program".to_string(), "-x".to_string()])
+            .run(&mut output);
+        assert_eq!(String::from_utf8(output).unwrap(), "2\nprogram\n-x\n");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Runtime error: Program exited with exit status: 2"
+        );
+
+        // Returned exit code of zero is not an error
+        let mut output: Vec<u8> = Vec::new();
+        let result = Compiler::new()
+            .add_source("main.rot", "fun main() -> int { 1 print 0 }")
+            .run(&mut output);
+        assert_eq!(result.unwrap(), vec![]);
+        assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn vm_stack_trace() {
+        let (result, _) = run_test_file("vm_stack_trace.rot");
+        match result {
+            Err(CompilerError::RuntimeError(None, message)) => assert_eq!(
+                message,
+                format!(
+                    "Division by zero\n  at divide ({TEST_FOLDER}/vm_stack_trace.rot:6:9)\n  at main ({TEST_FOLDER}/vm_stack_trace.rot:2:7)"
+                )
+            ),
+            _ => panic!("Expected runtime error"),
+        }
+    }
+}
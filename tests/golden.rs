@@ -0,0 +1,161 @@
+//! Golden-file tests for Rot programs.
+//!
+//! Every `.rot` file under `tests/` with expectations is compiled and run
+//! with `rot run`. The expectations are stored in files next to the program:
+//! - `<name>.stdout`: Expected output of the program
+//! - `<name>.stderr`: Expected compiler diagnostics and runtime errors
+//! - `<name>.exit`: Expected exit code, zero by default
+//!
+//! Programs with a `<name>.args` file are run with the `rot` subcommand and
+//! options in it instead of `run`, e.g. `check` to report all the errors and
+//! warnings instead of stopping at the first error.
+//!
+//! Files without any expectations are skipped. Run with `--bless` to update
+//! the expectations of the tests to the current results:
+//!
+//! ```text
+//! cargo test --test golden -- --bless
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const TEST_FOLDER: &str = "tests";
+
+fn main() {
+    let bless: bool = std::env::args().any(|arg| arg == "--bless");
+    let mut rot_files: Vec<PathBuf> = Vec::new();
+    find_rot_files(Path::new(TEST_FOLDER), &mut rot_files);
+    rot_files.sort();
+
+    let mut passed: usize = 0;
+    let mut failed: Vec<PathBuf> = Vec::new();
+    for rot_file in rot_files {
+        let Some(expected) = Expectation::read(&rot_file) else {
+            continue;
+        };
+        let actual: Expectation = run_rot_file(&rot_file);
+        if actual == expected {
+            passed += 1;
+        } else if bless {
+            actual.write(&rot_file);
+            println!("blessed {}", rot_file.display());
+            passed += 1;
+        } else {
+            println!("FAILED {}", rot_file.display());
+            print_diff(
+                "exit code",
+                &expected.exit_code.to_string(),
+                &actual.exit_code.to_string(),
+            );
+            print_diff("stdout", &expected.stdout, &actual.stdout);
+            print_diff("stderr", &expected.stderr, &actual.stderr);
+            failed.push(rot_file);
+        }
+    }
+
+    println!("golden: {passed} passed, {} failed", failed.len());
+    if !failed.is_empty() {
+        println!("Run `cargo test --test golden -- --bless` to update the expectations");
+        std::process::exit(1);
+    }
+}
+
+fn find_rot_files(folder: &Path, rot_files: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(folder).unwrap() {
+        let path: PathBuf = entry.unwrap().path();
+        if path.is_dir() {
+            find_rot_files(&path, rot_files);
+        } else if path.extension().is_some_and(|extension| extension == "rot") {
+            rot_files.push(path);
+        }
+    }
+}
+
+/// Result of compiling and running a Rot program
+#[derive(Debug, PartialEq)]
+struct Expectation {
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+impl Expectation {
+    /// Read the expectations of the program or `None` if it has none
+    fn read(rot_file: &Path) -> Option<Self> {
+        let exit_code: Option<i32> = std::fs::read_to_string(rot_file.with_extension("exit"))
+            .ok()
+            .map(|code| code.trim().parse().unwrap());
+        let stdout: Option<String> =
+            std::fs::read_to_string(rot_file.with_extension("stdout")).ok();
+        let stderr: Option<String> =
+            std::fs::read_to_string(rot_file.with_extension("stderr")).ok();
+        if exit_code.is_none() && stdout.is_none() && stderr.is_none() {
+            return None;
+        }
+        Some(Expectation {
+            exit_code: exit_code.unwrap_or(0),
+            stdout: stdout.unwrap_or_default(),
+            stderr: stderr.unwrap_or_default(),
+        })
+    }
+
+    /// Update the expectations of the program. Empty outputs and
+    /// the default exit code are not stored unless nothing else is.
+    fn write(&self, rot_file: &Path) {
+        let exit_code: String = match self.exit_code {
+            0 if !self.stdout.is_empty() || !self.stderr.is_empty() => String::new(),
+            code => format!("{code}\n"),
+        };
+        for (extension, contents) in [
+            ("stdout", &self.stdout),
+            ("stderr", &self.stderr),
+            ("exit", &exit_code),
+        ] {
+            let file: PathBuf = rot_file.with_extension(extension);
+            if contents.is_empty() {
+                let _ = std::fs::remove_file(file);
+            } else {
+                std::fs::write(file, contents).unwrap();
+            }
+        }
+    }
+}
+
+fn run_rot_file(rot_file: &Path) -> Expectation {
+    let args: String = std::fs::read_to_string(rot_file.with_extension("args"))
+        .unwrap_or_else(|_| "run".to_string());
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_rot-lang"))
+        .args(args.split_whitespace())
+        .arg(rot_file)
+        .output()
+        .unwrap();
+    Expectation {
+        exit_code: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    }
+}
+
+/// Print the differing lines of the expected and the actual output
+fn print_diff(name: &str, expected: &str, actual: &str) {
+    if expected == actual {
+        return;
+    }
+    println!("  {name}:");
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(expected), Some(actual)) if expected == actual => println!("     {expected}"),
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    println!("    -{expected}");
+                }
+                if let Some(actual) = actual {
+                    println!("    +{actual}");
+                }
+            }
+        }
+    }
+}